@@ -1,15 +1,21 @@
 use bevy::{asset::*, ecs::query::*, image::*, input::mouse::*, math::*, prelude::*};
-use bevy::render::{extract_resource::*, render_graph::*, render_resource::*, renderer::*, view::*, *};
+use bevy::render::{diagnostic::RenderDiagnosticsPlugin, extract_resource::*, render_phase::TrackedRenderPass, render_resource::*, view::*, *};
 use bevy::core_pipeline::core_2d::graph::*;
 use chain_link::{Length, L};
 use extract_component::*;
 use ndex::{Index, IndexMut};
-use crate::{*, attach::*, wgputil::*};
+use crate::{*, attach::*, graph::*, node::*, persist::*, status::*, texel::*, wgputil::*, wgsl::*};
 
 // TODO this is almost set up to work with multiple views, but not quite compatible yet
 //      we need a per-camera MouseDrawing component, not a global MouseDrawing resource
 
 const MIN_BRUSH_SIZE: f32 = 8.0;
+const CANVAS_FILE_STEM: &str = "draw_canvas";
+
+// shaders are embedded in the crate, so apps depending on it don't need to ship them in their assets folder
+// replace either with ShaderOverrideExt::override_shader(DRAW_SHADER, "your/own.wgsl")
+pub const DRAW_SHADER: &str = "embedded://bevy_micro_tools/shaders/draw.wgsl";
+pub const PASSTHROUGH_SHADER: &str = "embedded://bevy_micro_tools/shaders/passthrough.wgsl";
 
 pub struct DrawPlugin;
 
@@ -17,51 +23,72 @@ impl Plugin for DrawPlugin {
 
     fn build(&self, app: &mut App) {
 
+        // registered under DRAW_SHADER and PASSTHROUGH_SHADER, "programs" is this file's source root
+        embedded_asset!(app, "programs", "shaders/draw.wgsl");
+        embedded_asset!(app, "programs", "shaders/passthrough.wgsl");
+
         // core for generating the mouse trail and inputs to the draw shader
         app.insert_resource(MouseDrawing { min_brush_size: MIN_BRUSH_SIZE, ..default() });
         app.add_plugins(ExtractResourcePlugin::<MouseDrawing>::default());
         app.add_systems(Update, mouse_drawing_system);
+        app.add_plugins(WgslModulePlugin::<DrawParams>::new("bevy_micro_tools::draw_params"));
+        // DrawParams changes every frame, so it's written to a ring rather than a uniform buffer and bind group of its own
+        // added before the PassGraph below, since the draw pipeline takes the ring's layout in finish
+        app.add_plugins(UniformRingPlugin::<DrawParams>::default());
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.add_systems(Render, prepare_draw_params.in_set(RenderSet::Prepare));
+        }
 
         // required for auto-resizing the draw canvas
         // we can't use the screen output as canvas since it's not persistent
         app.add_plugins(AttachPlugin::<DrawCanvas, AndExtract>::default());
+        app.register_type::<DrawCanvas>(); // visible to inspectors and loadable from scenes
+        app.add_plugins(AttachFilePlugin::<DrawCanvas, 0>::default()); // saves and loads the canvas, reading it back through ReadbackPlugin
+        app.add_systems(Update, canvas_file_system);
+
+        // the passthrough writes to the view target, so its pipeline follows each camera's format
+        app.add_plugins(SpecializedRasterPlugin::<Passthrough>::default());
+        app.add_plugins(ShaderDefSettingsPlugin::<Passthrough, DrawSettings>::default());
+        app.add_systems(Update, draw_settings_system);
+
+        // keeps the passthrough from recreating its canvas bind group every frame
+        app.add_plugins(BindGroupCachePlugin);
+
+        // logs shader compile errors once per pass and sends PipelineStatusChanged events
+        app.add_plugins(PipelineStatusPlugin::<DrawCanvasPass>::default());
+        app.add_plugins(PipelineStatusPlugin::<Passthrough>::default());
+
+        // per-pass GPU/CPU timings, read them with pass_diagnostic_path::<DrawCanvasPass>("elapsed_gpu")
+        if !app.is_plugin_added::<RenderDiagnosticsPlugin>() {
+            app.add_plugins(RenderDiagnosticsPlugin);
+        }
 
         // create a 2d camera with the DrawCanvas component, which will be automatically resized for us
         app.add_systems(Startup, |mut commands: Commands| {
             commands.spawn((
                 DrawCanvas::default(), // our extractable auto-resizing attachment image(s)
+                DrawSettings::default(), // per-camera shader toggles
                 Camera2d::default(), // the camera which will serve as our view target
             ));
         });
 
-        // initialize the custom render passes that let us draw to the screen
-        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
-            render_app.add_render_graph_node::<ViewNodeRunner<DrawCanvasPass>>(Core2d, DrawCanvasPass);
-            render_app.add_render_graph_node::<ViewNodeRunner<Passthrough>>(Core2d, Passthrough);
-            render_app.add_render_graph_edges(Core2d, (
-                Node2d::StartMainPass,
-                DrawCanvasPass, // this will add the trail increment to the persistent DrawCanvas
-                Node2d::Tonemapping, 
-                Passthrough, // this copies that canvas over to the actual screen render target
-                Node2d::EndMainPassPostProcessing,
-            ));
-        };
-    }
-
-    fn finish(&self, app: &mut App) {
-        // initialize the custom render pass pipelines
-        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
-            render_app.init_resource::<RasterPipeline<DrawCanvasPass>>();
-            render_app.init_resource::<RasterPipeline<Passthrough>>();
-        }
+        // the custom render passes that let us draw to the screen, their pipelines are created in finish
+        app.add_plugins(PassGraph::new(Core2d)
+            // this will add the trail increment to the persistent DrawCanvas
+            .raster::<DrawCanvasPass>(PassOrder::between(Node2d::StartMainPass, Node2d::Tonemapping))
+            // this copies that canvas over to the actual screen render target
+            .raster::<Passthrough>(PassOrder::between(Node2d::Tonemapping, Node2d::EndMainPassPostProcessing)));
     }
 }
 
 // auto-resizing image attachment, which also doubles as a bind group impl
-#[derive(Index, IndexMut, Component, Default, Clone, ExtractComponent, AsBindGroup)]
+// the handle is skipped when serializing, so scenes spawn it as default and AttachPlugin sizes it on load
+#[derive(Index, IndexMut, Component, Default, Clone, ExtractComponent, AsBindGroup, Reflect)]
+#[reflect(Component, Default, Attach)]
 pub struct DrawCanvas {
     #[index(0)]
     #[texture(0, filterable = false, visibility(all))]
+    #[reflect(skip_serializing)]
     handle: Handle<Image>,
 }
 
@@ -219,89 +246,133 @@ pub fn mouse_drawing_system(
     mouse_trail.last_pos = Some(xy);
 }
 
-// params passed to the draw.wgsl shader
-#[derive(Default, Copy, Clone, ShaderType)]
-pub struct DrawParams {
-    quad: [Vec4; 4], // next quad in the mouse trail
-    brush: u32, // which brush is currently selected
+// Ctrl+S saves the canvas to disk and Ctrl+O loads it back, so drawings persist between sessions
+pub fn canvas_file_system(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    cameras: Query<Entity, With<DrawCanvas>>,
+) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+    // the extension follows the canvas format, so it's EXR for Rgba32Float
+    let Ok(file_format) = FileFormat::for_texture(DrawCanvas::TEXTURE_FORMAT) else {
+        return;
+    };
+    let path = format!("{CANVAS_FILE_STEM}.{}", file_format.extension());
+    for camera in &cameras {
+        if keys.just_pressed(KeyCode::KeyS) {
+            commands.entity(camera).save_attachment::<DrawCanvas, 0>(&path);
+        } else if keys.just_pressed(KeyCode::KeyO) {
+            commands.entity(camera).load_attachment::<DrawCanvas, 0>(&path);
+        }
+    }
+}
+
+// per-camera toggles for the passthrough shader, each combination gets its own pipeline variant
+#[derive(Component, Default, Clone, ExtractComponent)]
+pub struct DrawSettings {
+    pub debug_overlay: bool, // checkerboard behind the transparent parts of the canvas
+}
+
+impl ShaderDefSettings for DrawSettings {
+    fn shader_defs(&self) -> Vec<ShaderDefVal> {
+        let mut shader_defs = vec![];
+        if self.debug_overlay {
+            shader_defs.push("DEBUG_OVERLAY".into());
+        }
+        shader_defs
+    }
+}
+
+// F3 toggles the debug overlay
+pub fn draw_settings_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: Query<&mut DrawSettings>,
+) {
+    if keys.just_pressed(KeyCode::F3) {
+        for mut settings in &mut settings {
+            settings.debug_overlay = !settings.debug_overlay;
+        }
+    }
+}
+
+// params passed to the draw.wgsl shader, which imports the generated struct from bevy_micro_tools::draw_params
+wgsl_struct! {
+    #[derive(Default, Copy, Clone, ShaderType)]
+    pub struct DrawParams {
+        quad: [Vec4; 4], // next quad in the mouse trail
+        brush: u32, // which brush is currently selected
+    }
 }
 
 define_render_pass_struct!(DrawCanvasPass);
 
+// binds the DrawParams ring itself at group 0, right after the empty Binds
 impl Pass for DrawCanvasPass {
-    type Binds = (Uniform<DrawParams>,);
+    type Binds = ();
+    type Constants = ();
+
+    fn extra_layouts(world: &World) -> Vec<BindGroupLayout> {
+        vec![world.resource::<UniformRing<DrawParams>>().layout().clone()]
+    }
+
+    fn extra_layout_entries() -> Vec<Vec<BindGroupLayoutEntry>> {
+        vec![UniformRing::<DrawParams>::layout_entries()]
+    }
 }
 
 impl Raster for DrawCanvasPass {
-    const VERTEX_FRAGMENT_SHADER_PATH: &'static str = "shaders/draw.wgsl";
+    const VERTEX_FRAGMENT_SHADER_PATH: &'static str = DRAW_SHADER;
 
     fn fragment_targets() -> Vec<Option<ColorTargetState>> {
         vec![Some(DrawCanvas::color_target_state())] 
     }
 }
 
-impl ViewNode for DrawCanvasPass {
-
-    type ViewQuery = &'static DrawCanvas;
-
-    fn run(
-        &self, 
-        _: &mut RenderGraphContext, 
-        context: &mut RenderContext, 
-        canvas: QueryItem<Self::ViewQuery>, 
-        world: &World
-    ) -> Result<(), NodeRunError> {
-
-        // TODO this could be a view on the camera and we'll just render per-camera and it'll be great and ...
-        let MouseDrawing { 
-            continuation: true, 
-            last_quad: Some(quad),
-            brush_type,
-            .. 
-        } = world.resource::<MouseDrawing>() else {
-            return Ok(());
-        };
-        let uniform = DrawParams {
-            quad: *quad,
-            brush: *brush_type,
-        }.into_uniform();
-        
-        let pipelines = world.resource::<PipelineCache>();
-        let draw_pipeline = world.resource::<RasterPipeline<Self>>();
-        let Some(pipeline) = pipelines.get_render_pipeline(draw_pipeline.id()) else { 
-            warn!("Missing Draw Pipeline");
-            return Ok(());
-        };
+impl RasterPass for DrawCanvasPass {
 
-        let device = context.render_device();
-        let params = &mut get_binding_group_params(world);
-        let group0 = uniform.as_bind_group(&draw_pipeline[0], device, params).unwrap().bind_group;
+    // views without ViewDrawParams are skipped by the node runner, i.e. unless there's a new quad in the mouse trail
+    type ViewQuery = (&'static DrawCanvas, &'static ViewDrawParams);
 
-        let Some(canvas) = params.0.get(&canvas[0]) else {
-            warn!("Missing DrawCanvas GPU Image");
-            return Ok(());
-        };
-        let canvas = RenderPassColorAttachment {
-            view: &canvas.texture.create_view(&DrawCanvas::texture_view(canvas.size).descriptor()),
-            resolve_target: None,
-            ops: Operations {
-                load: LoadOp::Load,
-                store: StoreOp::Store
-            }
-        };
+    fn bind_groups(_: &QueryItem<Self::ViewQuery>, _: &World) -> Option<Self::Binds> {
+        Some(())
+    }
 
-        let descriptor = RenderPassDescriptor {
-            label: Some("Draw Pass"),
-            color_attachments: &[Some(canvas)],
-            depth_stencil_attachment: None,
-            ..default()
-        };
-        let mut render_pass = context.command_encoder().begin_render_pass(&descriptor);
-        render_pass.set_pipeline(pipeline);
-        render_pass.set_bind_group(0, &group0, &[]);
-        render_pass.draw(0..4, 0..1);
+    fn color_targets((canvas, _): &QueryItem<Self::ViewQuery>, world: &World) -> Option<Vec<ColorTarget>> {
+        Some(vec![ColorTarget::load(attach_view::<DrawCanvas, 0>(canvas, world)?)])
+    }
+
+    fn draw((_, params): &QueryItem<Self::ViewQuery>, world: &World, render_pass: &mut TrackedRenderPass) {
+        world.resource::<UniformRing<DrawParams>>().set_render(render_pass, 0, params.offset);
+        render_pass.draw(0..Self::VERTEX_COUNT, 0..1);
+    }
+}
 
-        Ok(())
+// offset of a view's DrawParams in this frame's ring, only present while there's a new quad to draw
+#[derive(Component)]
+pub struct ViewDrawParams {
+    offset: u32,
+}
+
+fn prepare_draw_params(
+    mut commands: Commands,
+    mut ring: ResMut<UniformRing<DrawParams>>,
+    mouse_drawing: Res<MouseDrawing>,
+    views: Query<Entity, With<DrawCanvas>>,
+) {
+    // TODO this could come from a per-camera MouseDrawing once it's extracted per view
+    let params = match &*mouse_drawing {
+        MouseDrawing { continuation: true, last_quad: Some(quad), brush_type, .. } => {
+            Some(DrawParams { quad: *quad, brush: *brush_type })
+        }
+        _ => None,
+    };
+    for view in &views {
+        match params {
+            Some(params) => commands.entity(view).insert(ViewDrawParams { offset: ring.push(&params) }),
+            None => commands.entity(view).remove::<ViewDrawParams>(),
+        };
     }
 }
 
@@ -309,66 +380,72 @@ define_render_pass_struct!(Passthrough);
 
 impl Pass for Passthrough {
     type Binds = (DrawCanvas,);
+    type Constants = ();
 }
 
-// TODO make separate vertex shader for fullscreen
-//      and then decouple the shader requirement so we can just point to that fullscreen shader
-//      or better yet use bevy's built-in fullscreen post process vertex shader
-impl Raster for Passthrough {
-    const VERTEX_FRAGMENT_SHADER_PATH: &'static str = "shaders/passthrough.wgsl";
+// drawn with bevy's fullscreen vertex shader, so passthrough.wgsl only has the fragment stage
+impl FullscreenRaster for Passthrough {
+    const FRAGMENT_SHADER_PATH: &'static str = PASSTHROUGH_SHADER;
 
     fn fragment_targets() -> Vec<Option<ColorTargetState>> {
-        vec![Some(TextureFormat::bevy_default().into())] 
+        vec![Some(TextureFormat::bevy_default().into())] // replaced by the view's format in SpecializedRaster
+    }
+}
+
+// writes to the single-sampled post process textures, so only the format follows the view
+impl SpecializedRaster for Passthrough {
+    fn specialize(key: RasterKey, descriptor: &mut RenderPipelineDescriptor) {
+        set_target_formats(key.format, descriptor);
     }
 }
 
-impl ViewNode for Passthrough {
+impl RasterPass for Passthrough {
 
     type ViewQuery = (
         &'static ViewTarget,
         &'static DrawCanvas,
     );
 
-    fn run(
-        &self, 
-        _: &mut RenderGraphContext, 
-        context: &mut RenderContext, 
-        (view, canvas): QueryItem<Self::ViewQuery>, 
-        world: &World
-    ) -> Result<(), NodeRunError> {
-        
-        let pipelines = world.resource::<PipelineCache>();
-        let passthrough_pipeline = world.resource::<RasterPipeline<Self>>();
-        let Some(pipeline) = pipelines.get_render_pipeline(passthrough_pipeline.id()) else { 
-            warn!("Missing Passthrough Pipeline");
-            return Ok(());
-        };
-
-        let device = context.render_device();
-        let params = &mut get_binding_group_params(world);
-        let Ok(group0) = canvas.as_bind_group(&passthrough_pipeline[0], device, params) else {
-            warn!("Missing???");
-            return Ok(());
-        };
+    fn bind_groups((_, canvas): &QueryItem<Self::ViewQuery>, _: &World) -> Option<Self::Binds> {
+        Some(((*canvas).clone(),))
+    }
 
+    fn color_targets((view, _): &QueryItem<Self::ViewQuery>, _: &World) -> Option<Vec<ColorTarget>> {
         let post_process = view.post_process_write();
-        let attachment = RenderPassColorAttachment {
-            view: post_process.destination,
-            resolve_target: None,
-            ops: default(),
-        };
+        Some(vec![ColorTarget::clear(post_process.destination.clone(), LinearRgba::NONE)])
+    }
+}
 
-        let descriptor = RenderPassDescriptor {
-            label: Some("Passthrough"),
-            color_attachments: &[Some(attachment)],
-            depth_stencil_attachment: None,
-            ..default()
-        };
-        let mut render_pass = context.command_encoder().begin_render_pass(&descriptor);
-        render_pass.set_pipeline(pipeline);
-        render_pass.set_bind_group(0, &group0.bind_group, &[]);
-        render_pass.draw(0..4, 0..1);
+#[cfg(test)]
+mod tests {
+    use crate::validate::*;
+    use super::*;
 
-        Ok(())
+    fn device_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(fallback_render_device().expect("no wgpu adapter to build the pass layouts on"));
+        world.init_resource::<UniformRing<DrawParams>>();
+        world
+    }
+
+    #[test]
+    fn draw_shader_matches_draw_canvas_pass() {
+        let draw_params = Shader::from_wgsl(wgsl_module::<DrawParams>("bevy_micro_tools::draw_params"), "draw_params.wgsl");
+        let shader = Shader::from_wgsl(include_str!("shaders/draw.wgsl"), DRAW_SHADER);
+        let result = BindingValidator::new().with_import(&draw_params).unwrap()
+            .validate::<DrawCanvasPass>(&device_world(), &shader, vec![]);
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn passthrough_shader_matches_passthrough_with_every_setting() {
+        let world = device_world();
+        let fullscreen = Shader::from_wgsl(FULLSCREEN_STAND_IN, "fullscreen.wgsl");
+        let shader = Shader::from_wgsl(include_str!("shaders/passthrough.wgsl"), PASSTHROUGH_SHADER);
+        let mut validator = BindingValidator::new().with_import(&fullscreen).unwrap();
+        for debug_overlay in [false, true] {
+            let shader_defs = DrawSettings { debug_overlay }.shader_defs();
+            assert_eq!(validator.validate::<Passthrough>(&world, &shader, shader_defs), Ok(()), "debug_overlay: {debug_overlay}");
+        }
     }
 }
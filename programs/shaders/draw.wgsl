@@ -1,10 +1,7 @@
-@group(0) @binding(0)
-var<uniform> u: Uniform;
+#import bevy_micro_tools::draw_params::DrawParams
 
-struct Uniform {
-    quad: array<vec4<f32>, 4>,
-    brush: u32,
-}
+@group(0) @binding(0)
+var<uniform> u: DrawParams;
 
 @vertex
 fn vertex(@builtin(vertex_index) corner: u32) -> @builtin(position) vec4<f32> {
@@ -0,0 +1,17 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+@group(0) @binding(0)
+var draw_canvas: texture_2d<f32>;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let color = textureLoad(draw_canvas, vec2<u32>(in.position.xy), 0);
+#ifdef DEBUG_OVERLAY
+    // checkerboard behind the canvas, so transparent and erased regions are visible
+    let cell = vec2<u32>(in.position.xy) / 16u;
+    let checker = vec4<f32>(vec3<f32>(select(0.2, 0.3, (cell.x + cell.y) % 2u == 0u)), 1.0);
+    return mix(checker, color, color.a);
+#else
+    return color;
+#endif
+}
@@ -1,9 +1,10 @@
 use std::{marker::*, ops::*};
-use bevy::{app::*, asset::*, ecs::component::*, image::*, math::*, prelude::*};
-use bevy::render::{extract_component::*, render_resource::*};
+use bevy::{app::*, asset::*, ecs::{component::*, entity::EntityHashSet}, image::*, math::*, prelude::*};
+use bevy::render::{extract_component::*, render_resource::*, sync_world::RenderEntity, *};
+use bevy::reflect::FromType;
 use chain_link::*;
 
-use crate::wgputil::ImageViewBuilder;
+use crate::wgputil::{ImageTextureBuilder, ImageViewBuilder};
 
 #[derive(Default)]
 pub struct AndExtract;
@@ -19,7 +20,10 @@ where
     for<'a> AttachPlugin::<A, ()>: Cascade<In<'a> = AttachParams<'a, A>>,
 {
     fn build(&self, app: &mut App) {
-        app.add_systems(PostUpdate, resize_cascade_system::<A>); // TODO optimal (or configurable) schedule?
+        app.add_systems(PostUpdate, (
+            resize_cascade_system::<A>, // TODO optimal (or configurable) schedule?
+            resize_shared_system::<A>,
+        ));
     }
 }
 
@@ -30,7 +34,9 @@ where
     fn build(&self, app: &mut App) {
         app.add_plugins(AttachPlugin::<A, ()>::default());
         app.add_plugins(ExtractComponentPlugin::<A>::default());
-
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.add_systems(ExtractSchedule, extract_shared_system::<A>);
+        }
     }
 }
 
@@ -47,34 +53,45 @@ where
 
     fn chain((images, attach, physical_target_size): Self::In<'_>) -> Self::Out<'_> {
         let new_size = A::compute_size(physical_target_size);
-        let handle = &mut attach[N];
-        // TODO why is it possible for the default handle to be a valid asset?
-        if let Handle::Weak(AssetId::Uuid { uuid: AssetId::<Image>::DEFAULT_UUID }) = handle {
-            debug!("Replacing default handle with new image");
-            *handle = images.add(A::new_image(new_size));
-        } else if let Some(image) = images.get(&*handle) {
-            if image.texture_descriptor.size != new_size {
-                // TRACK https://github.com/bevyengine/bevy/pull/19462
-                if A::COPY_ON_RESIZE {
-                    debug!("Copy-on-resize -> {physical_target_size:?}");
-                    images.get_mut(handle).unwrap().resize_in_place(new_size);
-                } else {
-                    debug!("Default resize -> {physical_target_size:?}");
-                    images.get_mut(handle).unwrap().texture_descriptor.size = new_size; // TODO breaks for data: Some(..)?
-                }
+        resize_attachment(images, &mut attach[N], new_size, A::COPY_ON_RESIZE, A::new_image);
+        return (images, attach, physical_target_size)
+    }
+}
+
+/// Shared resize logic for both the compile-time `Attach<N>` cascade and the runtime `AttachVec`.
+/// Replaces default or dangling handles with a freshly allocated image, otherwise resizes in place.
+fn resize_attachment(
+    images: &mut Assets<Image>,
+    handle: &mut Handle<Image>,
+    new_size: Extent3d,
+    copy_on_resize: bool,
+    new_image: impl FnOnce(Extent3d) -> Image,
+) {
+    // TODO why is it possible for the default handle to be a valid asset?
+    if let Handle::Weak(AssetId::Uuid { uuid: AssetId::<Image>::DEFAULT_UUID }) = handle {
+        debug!("Replacing default handle with new image");
+        *handle = images.add(new_image(new_size));
+    } else if let Some(image) = images.get(&*handle) {
+        if image.texture_descriptor.size != new_size {
+            // TRACK https://github.com/bevyengine/bevy/pull/19462
+            if copy_on_resize {
+                debug!("Copy-on-resize -> {new_size:?}");
+                images.get_mut(handle).unwrap().resize_in_place(new_size);
+            } else {
+                debug!("Default resize -> {new_size:?}");
+                images.get_mut(handle).unwrap().texture_descriptor.size = new_size; // TODO breaks for data: Some(..)?
             }
-        } else {
-            debug!("Edge case: possibly valid handle, but no image found, creating new one");
-            *handle = images.add(A::new_image(new_size));
         }
-        return (images, attach, physical_target_size)
+    } else {
+        debug!("Edge case: possibly valid handle, but no image found, creating new one");
+        *handle = images.add(new_image(new_size));
     }
 }
 
 /// System to trigger a chain-link cascade through all of T's Attach<#> impls.
 /// Iterates from 0..=N, sequentially resizing each defined Attach<#> type.
 fn resize_cascade_system<A>(
-    mut query: Query<(&mut A, &Camera)>, 
+    mut query: Query<(&mut A, &Camera), Without<SharedAttach<A>>>, 
     mut images: ResMut<Assets<Image>>
 ) where
     A: Component<Mutability = Mutable>,
@@ -86,6 +103,103 @@ fn resize_cascade_system<A>(
     }
 }
 
+/// Same cascade as `resize_cascade_system`, but for shared attachments.
+/// The target size is resolved from every camera using the attachment, according to its `SharePolicy`.
+fn resize_shared_system<A>(
+    mut shared: Query<(Entity, &mut A, &SharedAttach<A>)>, 
+    cameras: Query<(Entity, &Camera, Option<&AttachTo<A>>)>,
+    mut images: ResMut<Assets<Image>>,
+    mut warned: Local<EntityHashSet>,
+) where
+    A: Component<Mutability = Mutable>,
+    for<'a> AttachPlugin::<A, ()>: Cascade<In<'a> = AttachParams<'a, A>>
+{
+    for (entity, mut attach, SharedAttach { policy, .. }) in &mut shared {
+        let uses = |camera: Entity, attach_to: Option<&AttachTo<A>>| camera == entity || attach_to.is_some_and(|to| to.entity == entity);
+        let sizes = cameras.iter()
+            .filter(|(camera, _, attach_to)| uses(*camera, *attach_to))
+            .filter_map(|(_, camera, _)| camera.physical_target_size());
+        let size = match policy {
+            SharePolicy::Largest => sizes.reduce(UVec2::max),
+            SharePolicy::Smallest => sizes.reduce(UVec2::min),
+            SharePolicy::Owner(owner) => match cameras.get(*owner) {
+                Ok((_, camera, attach_to)) if uses(*owner, attach_to) => {
+                    // warn again if the owner stops being valid later on
+                    warned.remove(&entity);
+                    camera.physical_target_size()
+                }
+                _ => {
+                    if warned.insert(entity) {
+                        warn!("Owner {owner} of shared attachment {entity} is not a camera using it, falling back to the largest target");
+                    }
+                    sizes.reduce(UVec2::max)
+                }
+            },
+        };
+        size.map(|size| AttachPlugin::<A, ()>::cascade((&mut images, &mut attach, size)));
+    }
+}
+
+/// Gives each camera's render entity the extracted shared attachment it points to,
+/// so view nodes can query `A::Out` exactly like for an attachment the camera owns.
+fn extract_shared_system<A: ExtractComponent>(
+    mut commands: Commands,
+    cameras: Extract<Query<(&RenderEntity, &AttachTo<A>)>>,
+    shared: Extract<Query<A::QueryData, (With<SharedAttach<A>>, A::QueryFilter)>>,
+) {
+    for (render_entity, attach_to) in &cameras {
+        match shared.get(attach_to.entity).ok().and_then(A::extract_component) {
+            Some(out) => commands.entity(render_entity.id()).insert(out),
+            None => commands.entity(render_entity.id()).remove::<A::Out>(),
+        };
+    }
+}
+
+/// How a shared attachment picks its size from the cameras using it.
+/// Component-wise, so `Largest` of 1920x1080 and 400x1200 is 1920x1200.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SharePolicy {
+    #[default]
+    Largest,
+    Smallest,
+    /// Always follow this camera's target size, regardless of the others.
+    /// It has to be the shared entity itself or a camera with an `AttachTo` it, otherwise `Largest` is used.
+    Owner(Entity),
+}
+
+/// Marks the entity's A as shared between cameras, which reference it through `AttachTo<A>`.
+/// If the entity is itself a camera, it counts as one of the users.
+#[derive(Component)]
+pub struct SharedAttach<A> {
+    pub policy: SharePolicy,
+    marker: PhantomData<A>,
+}
+
+impl<A> Default for SharedAttach<A> {
+    fn default() -> Self {
+        Self::new(default())
+    }
+}
+
+impl<A> SharedAttach<A> {
+    pub fn new(policy: SharePolicy) -> Self {
+        Self { policy, marker: PhantomData }
+    }
+}
+
+/// Placed on a camera to render with the shared A of another entity instead of owning one.
+#[derive(Component)]
+pub struct AttachTo<A> {
+    pub entity: Entity,
+    marker: PhantomData<A>,
+}
+
+impl<A> AttachTo<A> {
+    pub fn new(entity: Entity) -> Self {
+        Self { entity, marker: PhantomData }
+    }
+}
+
 pub trait Attach<const N: usize>
 where
     Self: InRange<N, <Self as Length>::Len>,
@@ -118,37 +232,441 @@ where
     }
 
     fn new_image(size: Extent3d) -> Image {
-        Image {
-            data: None,
-            texture_descriptor: TextureDescriptor {
-                label: Self::LABEL,
-                size,
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: TextureDimension::D2,
-                format: Self::TEXTURE_FORMAT,
-                usage: Self::TEXTURE_USAGES,
-                view_formats: &[],
-            },
-            texture_view_descriptor: Some(Self::texture_view(size).descriptor()),
-            ..default()
-        }
+        AttachDescriptor::of::<Self, N>().image(size, Self::texture_view(size))
     }
 
     fn texture_view(size: Extent3d) -> ImageViewBuilder<'static> {
+        AttachDescriptor::of::<Self, N>().texture_view(size)
+    }
+}
+
+
+// reflection metadata
+
+/// Reflected `Attach<N>` settings of a component, registered with `#[reflect(Attach)]`.
+/// Lets inspectors and scene tooling read the format, usages, etc. of every attachment index.
+#[derive(Clone)]
+pub struct ReflectAttach {
+    descriptors: fn() -> Vec<AttachDescriptor>,
+}
+
+impl ReflectAttach {
+    /// One descriptor per `Attach<N>` impl, ordered by N.
+    pub fn descriptors(&self) -> Vec<AttachDescriptor> {
+        (self.descriptors)()
+    }
+}
+
+impl<A> FromType<A> for ReflectAttach
+where
+    for<'a> AttachMeta<A>: Cascade<In<'a> = &'a mut Vec<AttachDescriptor>>,
+{
+    fn from_type() -> Self {
+        Self { 
+            descriptors: || {
+                let mut descriptors = vec![];
+                AttachMeta::<A>::cascade(&mut descriptors);
+                descriptors
+            }
+        }
+    }
+}
+
+/// Cascades through all of A's Attach<#> impls, collecting their settings as descriptors.
+pub struct AttachMeta<A>(PhantomData<A>);
+
+impl<A: Length> Length for AttachMeta<A> {
+    type Len = A::Len;
+}
+
+impl<const N: usize, A: Attach<N>> Chain<N> for AttachMeta<A>
+where 
+    Self: InRange<N, Self::Len>,
+{
+    type In<'a> = &'a mut Vec<AttachDescriptor>;
+    type Out<'a> = &'a mut Vec<AttachDescriptor>;
+
+    fn chain(descriptors: Self::In<'_>) -> Self::Out<'_> {
+        descriptors.push(AttachDescriptor::of::<A, N>());
+        return descriptors
+    }
+}
+
+
+// runtime attachment sets
+
+/// Runtime counterpart to the `Attach<N>` consts, for attachments decided while the app is running.
+/// Reflected as an opaque value, since the wgpu state types and `compute_size` aren't reflectable field by field.
+#[derive(Clone, Debug, Reflect)]
+#[reflect(opaque, Clone, Debug)]
+pub struct AttachDescriptor {
+    pub label: Option<&'static str>,
+    pub blend_state: Option<BlendState>,
+    pub color_writes: ColorWrites,
+    pub texture_aspect: TextureAspect,
+    pub copy_on_resize: bool,
+    pub texture_format: TextureFormat,
+    pub texture_usages: TextureUsages,
+    pub compute_size: fn(UVec2) -> Extent3d,
+}
+
+impl AttachDescriptor {
+    /// Same defaults as the `Attach<N>` trait, only format and usages are required.
+    pub fn new(texture_format: TextureFormat, texture_usages: TextureUsages) -> Self {
+        Self {
+            label: None,
+            blend_state: None,
+            color_writes: ColorWrites::ALL,
+            texture_aspect: TextureAspect::All,
+            copy_on_resize: false,
+            texture_format,
+            texture_usages,
+            compute_size: |UVec2 { x: width, y: height }| Extent3d { width, height, depth_or_array_layers: 1 },
+        }
+    }
+
+    /// Snapshot of a compile-time `Attach<N>` impl, so it can be pushed into an `AttachVec`.
+    pub fn of<A: Attach<N>, const N: usize>() -> Self {
+        Self {
+            label: A::LABEL,
+            blend_state: A::BLEND_STATE,
+            color_writes: A::COLOR_WRITES,
+            texture_aspect: A::TEXTURE_ASPECT,
+            copy_on_resize: A::COPY_ON_RESIZE,
+            texture_format: A::TEXTURE_FORMAT,
+            texture_usages: A::TEXTURE_USAGES,
+            compute_size: A::compute_size,
+        }
+    }
+
+    pub fn color_target_state(&self) -> ColorTargetState {
+        ColorTargetState {
+            format: self.texture_format,
+            blend: self.blend_state,
+            write_mask: self.color_writes,
+        }
+    }
+
+    pub fn compute_size(&self, physical_target_size: UVec2) -> Extent3d {
+        (self.compute_size)(physical_target_size)
+    }
+
+    pub fn new_image(&self, size: Extent3d) -> Image {
+        self.image(size, self.texture_view(size))
+    }
+
+    /// Image with these settings and the given view, so `Attach::new_image` keeps an overridden `texture_view`.
+    pub fn image(&self, size: Extent3d, view: ImageViewBuilder<'static>) -> Image {
+        ImageTextureBuilder::<'static>::default()
+            .label(self.label)
+            .size(size)
+            .format(self.texture_format)
+            .usage(self.texture_usages)
+            .view(Some(view.descriptor()))
+            .image()
+    }
+
+    pub fn texture_view(&self, size: Extent3d) -> ImageViewBuilder<'static> {
         ImageViewBuilder::<'static>::default()
-            .label(Self::LABEL)
-            .format(Some(Self::TEXTURE_FORMAT))
+            .label(self.label)
+            .format(Some(self.texture_format))
             .dimension(Some(match size.depth_or_array_layers {
                 0 => panic!("Cannot have 0 `depth_or_array_layers`"),
                 1 => TextureViewDimension::D2,
                 _ => TextureViewDimension::D2Array,
             }))
-            .usage(Some(Self::TEXTURE_USAGES))
-            .aspect(Self::TEXTURE_ASPECT)
+            .usage(Some(self.texture_usages))
+            .aspect(self.texture_aspect)
             .base_mip_level(0)
             .mip_level_count(None)
             .base_array_layer(0)
             .array_layer_count(None)
     }
-}
\ No newline at end of file
+}
+
+/// Variable-length set of auto-resizing attachments, the runtime alternative to `Length` + `Attach<N>`.
+/// Entries can be pushed or removed at any time, new entries get their image on the next resize pass.
+/// The `M` marker lets a single camera carry several independent sets.
+/// Visible to inspectors once registered, but not serializable into scenes since its descriptors are opaque.
+#[derive(Component, Reflect)]
+#[reflect(Component, Default, Clone)]
+pub struct AttachVec<M: Send + Sync + 'static = ()> {
+    attachments: Vec<(AttachDescriptor, Handle<Image>)>,
+    #[reflect(ignore)]
+    marker: PhantomData<M>,
+}
+
+impl<M: Send + Sync + 'static> Default for AttachVec<M> {
+    fn default() -> Self {
+        Self { attachments: vec![], marker: PhantomData }
+    }
+}
+
+impl<M: Send + Sync + 'static> Clone for AttachVec<M> {
+    fn clone(&self) -> Self {
+        Self { attachments: self.attachments.clone(), marker: PhantomData }
+    }
+}
+
+impl<M: Send + Sync + 'static> AttachVec<M> {
+    pub fn new(descriptors: impl IntoIterator<Item = AttachDescriptor>) -> Self {
+        let mut attach = Self::default();
+        descriptors.into_iter().for_each(|descriptor| { attach.push(descriptor); });
+        attach
+    }
+
+    /// Appends an attachment and returns its index, the image is allocated on the next resize pass.
+    pub fn push(&mut self, descriptor: AttachDescriptor) -> usize {
+        self.attachments.push((descriptor, Handle::default()));
+        self.attachments.len() - 1
+    }
+
+    pub fn remove(&mut self, index: usize) -> (AttachDescriptor, Handle<Image>) {
+        self.attachments.remove(index)
+    }
+
+    pub fn len(&self) -> usize { self.attachments.len() }
+
+    pub fn is_empty(&self) -> bool { self.attachments.is_empty() }
+
+    pub fn descriptor(&self, index: usize) -> &AttachDescriptor { &self.attachments[index].0 }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&AttachDescriptor, &Handle<Image>)> {
+        self.attachments.iter().map(|(descriptor, handle)| (descriptor, handle))
+    }
+
+    pub fn color_target_states(&self) -> Vec<Option<ColorTargetState>> {
+        self.attachments.iter().map(|(descriptor, _)| Some(descriptor.color_target_state())).collect()
+    }
+}
+
+impl<M: Send + Sync + 'static> Index<usize> for AttachVec<M> {
+    type Output = Handle<Image>;
+
+    fn index(&self, index: usize) -> &Self::Output { &self.attachments[index].1 }
+}
+
+impl<M: Send + Sync + 'static> IndexMut<usize> for AttachVec<M> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output { &mut self.attachments[index].1 }
+}
+
+impl<M: Send + Sync + 'static> ExtractComponent for AttachVec<M> {
+    type QueryData = &'static Self;
+    type QueryFilter = ();
+    type Out = Self;
+
+    fn extract_component(attach: &Self) -> Option<Self> { Some(attach.clone()) }
+}
+
+/// Runtime path of `AttachPlugin`, resizes every entry of an `AttachVec<M>` by iteration instead of a cascade.
+/// Extraction comes from the generic `AttachPlugin<A, AndExtract>` impl.
+impl<M: Send + Sync + 'static> Plugin for AttachPlugin<AttachVec<M>, ()> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, resize_vec_system::<M>);
+    }
+}
+
+fn resize_vec_system<M: Send + Sync + 'static>(
+    mut query: Query<(&mut AttachVec<M>, &Camera)>, 
+    mut images: ResMut<Assets<Image>>
+) {
+    for (mut attach, camera) in &mut query {
+        camera.physical_target_size()
+            .map(|size| attach.resize(&mut images, size));
+    }
+}
+
+impl<M: Send + Sync + 'static> AttachVec<M> {
+    /// Resizes every entry to its own `compute_size` of the target, allocating images for new entries.
+    fn resize(&mut self, images: &mut Assets<Image>, physical_target_size: UVec2) {
+        for (descriptor, handle) in &mut self.attachments {
+            let new_size = descriptor.compute_size(physical_target_size);
+            resize_attachment(images, handle, new_size, descriptor.copy_on_resize, |size| descriptor.new_image(size));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::{entity::EntityHashMap, system::RunSystemOnce};
+    use bevy::render::camera::{camera_system, ManualTextureViews, RenderTarget};
+    use bevy::scene::{ron, serde::SceneDeserializer, DynamicSceneBuilder};
+    use bevy::window::{WindowCreated, WindowResized, WindowScaleFactorChanged};
+    use super::*;
+
+    #[derive(Component, Default, Reflect)]
+    #[reflect(Component, Default, Attach)]
+    struct Gbuffer {
+        #[reflect(skip_serializing)]
+        color: Handle<Image>,
+        #[reflect(skip_serializing)]
+        depth: Handle<Image>,
+    }
+
+    impl Index<usize> for Gbuffer {
+        type Output = Handle<Image>;
+
+        fn index(&self, index: usize) -> &Self::Output {
+            match index {
+                0 => &self.color,
+                1 => &self.depth,
+                _ => panic!("Gbuffer has no attachment {index}"),
+            }
+        }
+    }
+
+    impl IndexMut<usize> for Gbuffer {
+        fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+            match index {
+                0 => &mut self.color,
+                1 => &mut self.depth,
+                _ => panic!("Gbuffer has no attachment {index}"),
+            }
+        }
+    }
+
+    impl Attach<0> for Gbuffer {
+        const TEXTURE_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+        const TEXTURE_USAGES: TextureUsages = TextureUsages::RENDER_ATTACHMENT;
+    }
+
+    impl Attach<1> for Gbuffer {
+        const TEXTURE_FORMAT: TextureFormat = TextureFormat::R32Float;
+        const TEXTURE_USAGES: TextureUsages = TextureUsages::RENDER_ATTACHMENT;
+
+        fn compute_size(size: UVec2) -> Extent3d {
+            Extent3d { width: size.x / 2, height: size.y / 2, depth_or_array_layers: 1 }
+        }
+    }
+
+    impl Length for Gbuffer {
+        type Len = L<2>;
+    }
+
+    /// World with just enough for `camera_system` to fill in the target size of cameras rendering to images.
+    fn camera_world() -> World {
+        let mut world = World::new();
+        world.init_resource::<Assets<Image>>();
+        world.init_resource::<ManualTextureViews>();
+        world.init_resource::<Events<WindowResized>>();
+        world.init_resource::<Events<WindowCreated>>();
+        world.init_resource::<Events<WindowScaleFactorChanged>>();
+        world.init_resource::<Events<AssetEvent<Image>>>();
+        world
+    }
+
+    /// Camera rendering to a fresh image of `size`, its target size is known after `update_cameras`.
+    fn camera(world: &mut World, size: UVec2) -> (Camera, Projection) {
+        let extent = Extent3d { width: size.x, height: size.y, depth_or_array_layers: 1 };
+        let image = Image::new_fill(extent, TextureDimension::D2, &[0; 4], TextureFormat::Rgba8Unorm, default());
+        let target = world.resource_mut::<Assets<Image>>().add(image);
+        (Camera { target: RenderTarget::Image(target.into()), ..default() }, Projection::default())
+    }
+
+    fn update_cameras(world: &mut World) {
+        world.run_system_once(camera_system).unwrap();
+    }
+
+    fn size_of(world: &World, handle: &Handle<Image>) -> UVec2 {
+        world.resource::<Assets<Image>>().get(handle).unwrap().size()
+    }
+
+    #[test]
+    fn scene_attachments_resize_on_load() {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Gbuffer>();
+        let descriptors = registry.read().get_type_data::<ReflectAttach>(std::any::TypeId::of::<Gbuffer>()).unwrap().descriptors();
+        assert_eq!(descriptors.iter().map(|descriptor| descriptor.texture_format).collect::<Vec<_>>(), [TextureFormat::Rgba16Float, TextureFormat::R32Float]);
+
+        let mut source = World::new();
+        source.insert_resource(registry.clone());
+        let saved = source.spawn(Gbuffer::default()).id();
+        let scene = DynamicSceneBuilder::from_world(&source).extract_entity(saved).build();
+        let serialized = scene.serialize(&registry.read()).unwrap();
+
+        let scene = ron::Options::default()
+            .from_str_seed(&serialized, SceneDeserializer { type_registry: &registry.read() })
+            .unwrap();
+        let mut world = camera_world();
+        world.insert_resource(registry.clone());
+        let mut entity_map = EntityHashMap::default();
+        scene.write_to_world(&mut world, &mut entity_map).unwrap();
+        let loaded = entity_map[&saved];
+        assert_eq!(world.get::<Gbuffer>(loaded).unwrap().color, Handle::default());
+
+        let camera = camera(&mut world, UVec2::new(800, 600));
+        world.entity_mut(loaded).insert(camera);
+        update_cameras(&mut world);
+        world.run_system_once(resize_cascade_system::<Gbuffer>).unwrap();
+        let gbuffer = world.get::<Gbuffer>(loaded).unwrap();
+        assert_eq!(size_of(&world, &gbuffer.color), UVec2::new(800, 600));
+        assert_eq!(size_of(&world, &gbuffer.depth), UVec2::new(400, 300));
+        assert_eq!(world.resource::<Assets<Image>>().get(&gbuffer.depth).unwrap().texture_descriptor.format, TextureFormat::R32Float);
+    }
+
+    #[test]
+    fn attach_vec_resizes_mixed_sizes() {
+        let mut images = Assets::<Image>::default();
+        let mut half = AttachDescriptor::new(TextureFormat::Rgba8Unorm, TextureUsages::TEXTURE_BINDING);
+        half.compute_size = |size| Extent3d { width: size.x / 2, height: size.y / 2, depth_or_array_layers: 1 };
+        let mut layered = AttachDescriptor::new(TextureFormat::R32Float, TextureUsages::STORAGE_BINDING);
+        layered.compute_size = |size| Extent3d { width: size.x, height: size.y, depth_or_array_layers: 4 };
+        let mut attach = AttachVec::<()>::new([
+            AttachDescriptor::new(TextureFormat::Rgba16Float, TextureUsages::RENDER_ATTACHMENT),
+            half,
+            layered,
+        ]);
+        let size = |attach: &AttachVec, images: &Assets<Image>, index: usize| images.get(&attach[index]).unwrap().texture_descriptor.size;
+
+        attach.resize(&mut images, UVec2::new(800, 600));
+        assert_eq!(size(&attach, &images, 0), Extent3d { width: 800, height: 600, depth_or_array_layers: 1 });
+        assert_eq!(size(&attach, &images, 1), Extent3d { width: 400, height: 300, depth_or_array_layers: 1 });
+        assert_eq!(size(&attach, &images, 2), Extent3d { width: 800, height: 600, depth_or_array_layers: 4 });
+        assert_eq!(images.get(&attach[2]).unwrap().texture_descriptor.format, TextureFormat::R32Float);
+        assert_eq!(images.get(&attach[2]).unwrap().texture_view_descriptor.as_ref().unwrap().dimension, Some(TextureViewDimension::D2Array));
+
+        // existing entries resize in place, an entry pushed in between gets its image on the same pass
+        let handles: Vec<_> = attach.iter().map(|(_, handle)| handle.clone()).collect();
+        let pushed = attach.push(AttachDescriptor::new(TextureFormat::Rgba8Unorm, TextureUsages::COPY_SRC));
+        attach.resize(&mut images, UVec2::new(1920, 1080));
+        assert_eq!(attach.iter().take(3).map(|(_, handle)| handle.clone()).collect::<Vec<_>>(), handles);
+        assert_eq!(size(&attach, &images, 0), Extent3d { width: 1920, height: 1080, depth_or_array_layers: 1 });
+        assert_eq!(size(&attach, &images, 1), Extent3d { width: 960, height: 540, depth_or_array_layers: 1 });
+        assert_eq!(size(&attach, &images, 2), Extent3d { width: 1920, height: 1080, depth_or_array_layers: 4 });
+        assert_eq!(size(&attach, &images, pushed), Extent3d { width: 1920, height: 1080, depth_or_array_layers: 1 });
+        assert_eq!(images.len(), 4);
+    }
+
+    /// Shared Gbuffer on an entity of its own, used by an 800x600 and a 400x1200 camera.
+    fn shared_size(policy: impl FnOnce([Entity; 2]) -> SharePolicy) -> (UVec2, UVec2) {
+        let mut world = camera_world();
+        let shared = world.spawn(Gbuffer::default()).id();
+        let cameras = [UVec2::new(800, 600), UVec2::new(400, 1200)].map(|size| {
+            let camera = camera(&mut world, size);
+            world.spawn((camera, AttachTo::<Gbuffer>::new(shared))).id()
+        });
+        world.entity_mut(shared).insert(SharedAttach::<Gbuffer>::new(policy(cameras)));
+        update_cameras(&mut world);
+        world.run_system_once(resize_shared_system::<Gbuffer>).unwrap();
+        let gbuffer = world.get::<Gbuffer>(shared).unwrap();
+        (size_of(&world, &gbuffer.color), size_of(&world, &gbuffer.depth))
+    }
+
+    #[test]
+    fn shared_attachment_takes_the_largest_target() {
+        assert_eq!(shared_size(|_| SharePolicy::Largest), (UVec2::new(800, 1200), UVec2::new(400, 600)));
+    }
+
+    #[test]
+    fn shared_attachment_takes_the_smallest_target() {
+        assert_eq!(shared_size(|_| SharePolicy::Smallest), (UVec2::new(400, 600), UVec2::new(200, 300)));
+    }
+
+    #[test]
+    fn shared_attachment_follows_its_owner() {
+        assert_eq!(shared_size(|[main, _]| SharePolicy::Owner(main)), (UVec2::new(800, 600), UVec2::new(400, 300)));
+        assert_eq!(shared_size(|[_, minimap]| SharePolicy::Owner(minimap)), (UVec2::new(400, 1200), UVec2::new(200, 600)));
+        // not a camera using the attachment
+        assert_eq!(shared_size(|_| SharePolicy::Owner(Entity::PLACEHOLDER)), (UVec2::new(800, 1200), UVec2::new(400, 600)));
+    }
+}
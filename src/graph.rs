@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+use bevy::render::{render_graph::*, RenderApp};
+
+use crate::{node::*, wgputil::*};
+
+/// Where a pass goes in the render graph, relative to existing nodes like bevy's `Node2d`/`Node3d` labels.
+#[derive(Clone, Copy, Debug)]
+pub enum PassOrder {
+    Before(InternedRenderLabel),
+    After(InternedRenderLabel),
+    Between(InternedRenderLabel, InternedRenderLabel),
+}
+
+impl PassOrder {
+    pub fn before(label: impl RenderLabel) -> Self {
+        Self::Before(label.intern())
+    }
+
+    pub fn after(label: impl RenderLabel) -> Self {
+        Self::After(label.intern())
+    }
+
+    pub fn between(after: impl RenderLabel, before: impl RenderLabel) -> Self {
+        Self::Between(after.intern(), before.intern())
+    }
+
+    /// Graph edges as `(output, input)` pairs placing `pass` in this order.
+    fn edges(self, pass: InternedRenderLabel) -> Vec<(InternedRenderLabel, InternedRenderLabel)> {
+        match self {
+            Self::Before(label) => vec![(pass, label)],
+            Self::After(label) => vec![(label, pass)],
+            Self::Between(after, before) => vec![(after, pass), (pass, before)],
+        }
+    }
+}
+
+/// Adds the `RasterNode`/`ComputeNode` of each pass to a render sub graph with its ordering,
+/// and initializes each pass's `RasterPipeline`/`PipelineCompute` in `Plugin::finish`.
+/// Passes are the unit structs from `define_render_pass_struct!`, which double as their node labels.
+/// ```ignore
+/// app.add_plugins(PassGraph::new(Core2d)
+///     .raster::<DrawCanvasPass>(PassOrder::between(Node2d::StartMainPass, Node2d::Tonemapping))
+///     .raster::<Passthrough>(PassOrder::between(Node2d::Tonemapping, Node2d::EndMainPassPostProcessing)));
+/// ```
+pub struct PassGraph {
+    graph: InternedRenderSubGraph,
+    passes: Vec<GraphPass>,
+}
+
+struct GraphPass {
+    label: InternedRenderLabel,
+    order: PassOrder,
+    add_node: fn(&mut SubApp, InternedRenderSubGraph, InternedRenderLabel),
+    init_pipeline: fn(&mut SubApp),
+}
+
+impl PassGraph {
+    pub fn new(graph: impl RenderSubGraph) -> Self {
+        Self { graph: graph.intern(), passes: vec![] }
+    }
+
+    pub fn raster<P: RasterPass + RenderLabel + Default>(mut self, order: PassOrder) -> Self {
+        self.passes.push(GraphPass {
+            label: P::default().intern(),
+            order,
+            add_node: |render_app, graph, label| {
+                render_app.add_render_graph_node::<ViewNodeRunner<RasterNode<P>>>(graph, label);
+            },
+            init_pipeline: |render_app| {
+                render_app.init_resource::<RasterPipeline<P>>();
+            },
+        });
+        self
+    }
+
+    pub fn compute<P: ComputeDispatch + RenderLabel + Default>(mut self, order: PassOrder) -> Self {
+        self.passes.push(GraphPass {
+            label: P::default().intern(),
+            order,
+            add_node: |render_app, graph, label| {
+                render_app.add_render_graph_node::<ViewNodeRunner<ComputeNode<P>>>(graph, label);
+            },
+            init_pipeline: |render_app| {
+                render_app.init_resource::<PipelineCompute<P>>();
+            },
+        });
+        self
+    }
+}
+
+impl Plugin for PassGraph {
+    fn build(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        // every node is added before any edge, so passes can be ordered relative to each other too
+        for pass in &self.passes {
+            (pass.add_node)(render_app, self.graph, pass.label);
+        }
+        for pass in &self.passes {
+            for (output, input) in pass.order.edges(pass.label) {
+                render_app.add_render_graph_edge(self.graph, output, input);
+            }
+        }
+    }
+
+    fn finish(&self, app: &mut App) {
+        // pipelines need the RenderDevice, which only exists once the renderer is initialized
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            for pass in &self.passes {
+                (pass.init_pipeline)(render_app);
+            }
+        }
+    }
+
+    // several programs can each add their own graph
+    fn is_unique(&self) -> bool {
+        false
+    }
+}
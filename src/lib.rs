@@ -1,5 +1,13 @@
 pub mod attach;
+pub mod graph;
+pub mod node;
+pub mod persist;
+pub mod readback;
+pub mod status;
+pub mod texel;
+pub mod validate;
 pub mod wgputil;
+pub mod wgsl;
 
 #[path = "../programs"]
 pub mod programs {
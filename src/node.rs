@@ -0,0 +1,456 @@
+use std::{any::*, marker::*, sync::{atomic::*, Mutex}};
+use bevy::{diagnostic::DiagnosticPath, ecs::{query::*, system::SystemState}, prelude::*};
+use bevy::render::{diagnostic::RecordDiagnostics, extract_component::*, render_asset::*, render_graph::*, render_phase::TrackedRenderPass, render_resource::*, renderer::*, texture::GpuImage};
+use bevy::render::{view::*, Render, RenderApp, RenderSet};
+
+use crate::{attach::Attach, wgputil::*};
+
+/// Declarative raster pass, run through `RasterNode<P>`.
+/// A pass only supplies where its bind groups come from, what it renders into and how it draws,
+/// the node takes care of pipeline lookup, bind group creation and beginning the render pass.
+pub trait RasterPass: Pass<Binds: AsBindGroups> + Raster + Send + Sync + 'static {
+    type ViewQuery: ReadOnlyQueryData;
+
+    /// Values for each bind group of `Pass::Binds`, `None` skips the pass for this view and frame.
+    fn bind_groups(view: &QueryItem<Self::ViewQuery>, world: &World) -> Option<Self::Binds>;
+
+    /// Color attachments in `Raster::fragment_targets` order, `None` if a GPU image isn't ready yet.
+    fn color_targets(view: &QueryItem<Self::ViewQuery>, world: &World) -> Option<Vec<ColorTarget>>;
+
+    /// Defaults to `Raster::VERTEX_COUNT` vertices, a quad as a triangle strip or a fullscreen triangle.
+    /// Passes with `Pass::Constants` set them here through `RasterNode::set_push_constants`.
+    fn draw(_: &QueryItem<Self::ViewQuery>, _: &World, render_pass: &mut TrackedRenderPass) {
+        render_pass.draw(0..Self::VERTEX_COUNT, 0..1);
+    }
+}
+
+/// A single color attachment of a `RasterPass`.
+pub struct ColorTarget {
+    pub view: TextureView,
+    pub resolve_target: Option<TextureView>,
+    /// `None` loads the existing contents, `Some` clears to that color first.
+    pub clear: Option<LinearRgba>,
+    pub store: StoreOp,
+}
+
+impl ColorTarget {
+    pub fn load(view: TextureView) -> Self {
+        Self { view, resolve_target: None, clear: None, store: StoreOp::Store }
+    }
+
+    pub fn clear(view: TextureView, color: LinearRgba) -> Self {
+        Self { view, resolve_target: None, clear: Some(color), store: StoreOp::Store }
+    }
+}
+
+/// View of attachment N of A as configured by `Attach::texture_view`, or `None` if its GPU image isn't ready yet.
+pub fn attach_view<A: Attach<N>, const N: usize>(attach: &A, world: &World) -> Option<TextureView> {
+    let gpu_image = world.resource::<RenderAssets<GpuImage>>().get(&attach[N])?;
+    Some(gpu_image.texture.create_view(&A::texture_view(gpu_image.size).descriptor()))
+}
+
+/// Generic `ViewNode` for any `RasterPass`, add it with `ViewNodeRunner<RasterNode<P>>`.
+/// The render pass is wrapped in a debug group and a diagnostics span named `pass_name::<P>()`, see `pass_diagnostic_path`.
+pub struct RasterNode<P: RasterPass> {
+    params: BindGroupParams<P::Binds>,
+    pipeline_checked: bool,
+    targets_missing: Failing,
+}
+
+impl<P: RasterPass> FromWorld for RasterNode<P> {
+    fn from_world(world: &mut World) -> Self {
+        Self { params: BindGroupParams::new(world), pipeline_checked: false, targets_missing: default() }
+    }
+}
+
+impl<P: RasterPass<Constants: PushConstants>> RasterNode<P> {
+    /// Sets the pass's constants for the draws that follow.
+    /// Uses push constants when the device supports them, otherwise a uniform at the group after `Pass::Binds`.
+    pub fn set_push_constants(render_pass: &mut TrackedRenderPass, world: &World, constants: &P::Constants) {
+        // the pipeline always has a slot since `P::Constants` has a range
+        if let Some(slot) = world.resource::<RasterPipeline<P>>().push_constants() {
+            slot.set_render(render_pass, world, constants);
+        }
+    }
+}
+
+impl<P: RasterPass> ViewNode for RasterNode<P> {
+
+    type ViewQuery = (P::ViewQuery, Option<&'static ViewRasterPipeline<P>>);
+
+    fn update(&mut self, world: &mut World) {
+        self.params.update(world);
+        check_pipeline::<P, RasterPipeline<P>>(&mut self.pipeline_checked, world);
+    }
+
+    fn run<'w>(
+        &self,
+        _: &mut RenderGraphContext,
+        context: &mut RenderContext<'w>,
+        view: QueryItem<'w, Self::ViewQuery>,
+        world: &'w World
+    ) -> Result<(), NodeRunError> {
+
+        let name = type_name::<P>();
+        let (view, specialized) = view;
+        let Some(binds) = P::bind_groups(&view, world) else {
+            return Ok(());
+        };
+
+        let pipelines = world.resource::<PipelineCache>();
+        let Some(raster_pipeline) = world.get_resource::<RasterPipeline<P>>() else {
+            return Ok(());
+        };
+        let id = specialized.map_or(raster_pipeline.id(), |specialized| specialized.id);
+        let Some(pipeline) = pipelines.get_render_pipeline(id) else {
+            // still compiling or failed, PipelineStatusPlugin<P> reports failures once instead of every frame
+            return Ok(());
+        };
+
+        let Some(groups) = self.params.create_or_report::<P>(&binds, raster_pipeline, context.render_device(), world) else {
+            return Ok(());
+        };
+
+        let targets = P::color_targets(&view, world);
+        if self.targets_missing.starts(targets.is_none()) {
+            warn!("Missing {name} GPU image for color targets, skipping the pass until it's ready");
+        }
+        let Some(targets) = targets else {
+            return Ok(());
+        };
+        let color_attachments = targets.iter()
+            .map(|target| Some(RenderPassColorAttachment {
+                view: &target.view,
+                resolve_target: target.resolve_target.as_deref(),
+                ops: Operations {
+                    load: target.clear.map_or(LoadOp::Load, |color| LoadOp::Clear(color.into())),
+                    store: target.store,
+                },
+            }))
+            .collect::<Vec<_>>();
+
+        let descriptor = RenderPassDescriptor {
+            label: Some(name),
+            color_attachments: &color_attachments,
+            depth_stencil_attachment: None,
+            ..default()
+        };
+        let diagnostics = context.diagnostic_recorder();
+        let mut render_pass = context.begin_tracked_render_pass(descriptor);
+        let pass_span = diagnostics.pass_span(&mut render_pass, pass_name::<P>());
+        render_pass.push_debug_group(pass_name::<P>());
+        render_pass.set_render_pipeline(pipeline);
+        for (index, group) in groups.iter().enumerate() {
+            render_pass.set_bind_group(index, &group.bind_group, &group.offsets);
+        }
+        P::draw(&view, world, &mut render_pass);
+        render_pass.pop_debug_group();
+        pass_span.end(&mut render_pass);
+
+        Ok(())
+    }
+}
+
+/// Path of a measurement recorded for pass P's node, e.g. `elapsed_gpu` or `elapsed_cpu` in milliseconds.
+/// Requires bevy's `RenderDiagnosticsPlugin`, GPU times also need `WgpuFeatures::TIMESTAMP_QUERY` and
+/// `TIMESTAMP_QUERY_INSIDE_PASSES`, only CPU times are recorded without them. Read the smoothed value
+/// from `DiagnosticsStore`, or print every pass with `LogDiagnosticsPlugin`.
+pub fn pass_diagnostic_path<P>(measurement: &str) -> DiagnosticPath {
+    DiagnosticPath::from_components(["render", pass_name::<P>(), measurement])
+}
+
+/// Per-view pipeline variants for a `SpecializedRaster` pass, picked up by `RasterNode<P>` in place of the unspecialized one.
+/// Requires `RasterPipeline<P>` to be initialized in the render app as usual.
+pub struct SpecializedRasterPlugin<P>(PhantomData<P>);
+
+impl<P> Default for SpecializedRasterPlugin<P> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<P: SpecializedRaster> Plugin for SpecializedRasterPlugin<P> {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractComponentPlugin::<RasterKeyBits<P>>::default());
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<SpecializedRenderPipelines<RasterPipeline<P>>>();
+            render_app.init_resource::<ViewShaderDefSources<P>>();
+            render_app.add_systems(Render, prepare_raster_pipelines::<P>.in_set(RenderSet::Prepare));
+        }
+    }
+}
+
+/// Extracts the camera's S and adds its shader defs to the `RasterKey` of pass P.
+/// Requires `SpecializedRasterPlugin<P>`.
+pub struct ShaderDefSettingsPlugin<P, S>(PhantomData<(P, S)>);
+
+impl<P, S> Default for ShaderDefSettingsPlugin<P, S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<P, S> Plugin for ShaderDefSettingsPlugin<P, S>
+where
+    P: SpecializedRaster,
+    S: ShaderDefSettings + ExtractComponent<Out = S>,
+{
+    fn build(&self, app: &mut App) {
+        // the same settings can feed several passes, but only needs extracting once
+        if !app.is_plugin_added::<ExtractComponentPlugin<S>>() {
+            app.add_plugins(ExtractComponentPlugin::<S>::default());
+        }
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<ViewShaderDefSources<P>>();
+            render_app.world_mut().resource_mut::<ViewShaderDefSources<P>>().push::<S>();
+        }
+    }
+}
+
+/// The specialized pipeline of pass P for the view it's on.
+#[derive(Component)]
+pub struct ViewRasterPipeline<P> {
+    pub id: CachedRenderPipelineId,
+    marker: PhantomData<P>,
+}
+
+fn prepare_raster_pipelines<P: SpecializedRaster>(
+    mut commands: Commands,
+    pipeline_cache: Res<PipelineCache>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<RasterPipeline<P>>>,
+    raster_pipeline: Res<RasterPipeline<P>>,
+    shader_def_sources: Res<ViewShaderDefSources<P>>,
+    views: Query<(EntityRef, &ViewTarget, Option<&Msaa>, Option<&RasterKeyBits<P>>), With<ExtractedView>>,
+) {
+    for (entity, target, msaa, bits) in &views {
+        let key = RasterKey {
+            format: target.main_texture_format(),
+            samples: msaa.map_or(1, Msaa::samples),
+            bits: bits.map_or(0, |bits| bits.bits),
+            shader_defs: shader_def_sources.shader_defs(entity),
+        };
+        let id = pipelines.specialize(&pipeline_cache, &raster_pipeline, key);
+        commands.entity(entity.id()).insert(ViewRasterPipeline::<P> { id, marker: PhantomData });
+    }
+}
+
+/// Logs once when a node's pipeline resource R was never initialized, after which the node skips every frame
+/// instead of panicking. `PassGraph` initializes it in `Plugin::finish`, wiring nodes by hand has to do the same.
+fn check_pipeline<P, R: Resource>(checked: &mut bool, world: &World) {
+    if !std::mem::replace(checked, true) && !world.contains_resource::<R>() {
+        error!("{} isn't initialized, add {} through PassGraph or init it in Plugin::finish", type_name::<R>(), pass_name::<P>());
+    }
+}
+
+/// Whether a node keeps failing at something, so it's reported on the first frame rather than every frame.
+/// Atomic since `ViewNode::run` only gets `&self`.
+#[derive(Default)]
+struct Failing(AtomicBool);
+
+impl Failing {
+    /// Records this frame's outcome, true on the first failure since the last success.
+    fn starts(&self, failed: bool) -> bool {
+        !self.0.swap(failed, Ordering::Relaxed) && failed
+    }
+}
+
+/// Cached `SystemState` for a `Binds` tuple's params, fetched safely from the render world while a node runs.
+/// Behind a mutex since `ViewNode::run` only gets `&self`.
+struct BindGroupParams<B: AsBindGroups> {
+    state: Mutex<SystemState<B::Param>>,
+    failing: Failing,
+}
+
+impl<B: AsBindGroups> BindGroupParams<B> {
+    fn new(world: &mut World) -> Self {
+        Self { state: Mutex::new(SystemState::new(world)), failing: default() }
+    }
+
+    /// Keeps any queries in the params in sync with the world's archetypes.
+    fn update(&mut self, world: &World) {
+        self.state.get_mut().unwrap().update_archetypes(world);
+    }
+
+    /// Bind groups for a pass, through the `BindGroupCache` when `BindGroupCachePlugin` is added.
+    /// Only called once the pass's pipeline exists, which initializes the `EmptyBindGroup`.
+    fn create<P: Pass<Binds = B> + 'static>(
+        &self,
+        binds: &B,
+        layouts: &B::Layout,
+        device: &RenderDevice,
+        world: &World,
+    ) -> Result<Vec<BoundGroup>, AsBindGroupError> {
+        let mut state = self.state.lock().unwrap();
+        let params = &mut state.get_manual(world);
+        let cache = world.get_resource::<BindGroupCache>().map(|cache| (cache, TypeId::of::<P>()));
+        binds.as_bind_groups(layouts, device, world.resource::<EmptyBindGroup>(), params, cache)
+    }
+
+    /// `create`, with failures reported once until the bind groups can be created again, `None` skips the pass.
+    /// Missing GPU resources are expected for the first frames while assets upload.
+    fn create_or_report<P: Pass<Binds = B> + 'static>(
+        &self,
+        binds: &B,
+        layouts: &B::Layout,
+        device: &RenderDevice,
+        world: &World,
+    ) -> Option<Vec<BoundGroup>> {
+        let result = self.create::<P>(binds, layouts, device, world);
+        if self.failing.starts(result.is_err()) {
+            match &result {
+                Err(AsBindGroupError::RetryNextUpdate) => warn!("Missing {} GPU resources for bind groups, retrying every frame", type_name::<P>()),
+                Err(error) => error!("Failed to create {} bind groups: {error:?}", type_name::<P>()),
+                Ok(_) => {}
+            }
+        }
+        result.ok()
+    }
+}
+
+/// Reuses the bind groups of `RasterNode` and `ComputeNode` passes across frames while their resources don't change.
+pub struct BindGroupCachePlugin;
+
+impl Plugin for BindGroupCachePlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<BindGroupCache>();
+            render_app.add_systems(Render, evict_bind_groups.in_set(RenderSet::Cleanup));
+        }
+    }
+}
+
+fn evict_bind_groups(mut cache: ResMut<BindGroupCache>) {
+    cache.evict_unused();
+}
+
+/// Declarative compute pass, run through `ComputeNode<P>`.
+/// Dispatch counts are derived from `Compute::WORKGROUP_SIZE`, so a pass only states how many invocations it needs.
+pub trait ComputeDispatch: Pass<Binds: AsBindGroups> + Compute + Send + Sync + 'static {
+    type ViewQuery: ReadOnlyQueryData;
+
+    /// Values for each bind group of `Pass::Binds`, `None` skips the pass for this view and frame.
+    fn bind_groups(view: &QueryItem<Self::ViewQuery>, world: &World) -> Option<Self::Binds>;
+
+    /// Invocation extents, one dispatch each in order, `None` if a GPU image isn't ready yet.
+    /// Use `attach_extent` to cover an attachment one invocation per texel.
+    fn dispatches(view: &QueryItem<Self::ViewQuery>, world: &World) -> Option<Vec<UVec3>>;
+
+    /// Runs before dispatch `index`, passes with `Pass::Constants` set them here through `ComputeNode::set_push_constants`.
+    fn before_dispatch(_: &QueryItem<Self::ViewQuery>, _: &World, _: &mut ComputePass, _index: usize) {}
+}
+
+/// Size of attachment N of A's GPU image, or `None` if it isn't ready yet.
+pub fn attach_extent<A: Attach<N>, const N: usize>(attach: &A, world: &World) -> Option<UVec3> {
+    let gpu_image = world.resource::<RenderAssets<GpuImage>>().get(&attach[N])?;
+    let Extent3d { width, height, depth_or_array_layers } = gpu_image.size;
+    Some(UVec3::new(width, height, depth_or_array_layers))
+}
+
+/// Generic `ViewNode` for any `ComputeDispatch`, add it with `ViewNodeRunner<ComputeNode<P>>`.
+/// Like `RasterNode`, the compute pass is wrapped in a debug group and a diagnostics span named `pass_name::<P>()`.
+pub struct ComputeNode<P: ComputeDispatch> {
+    params: BindGroupParams<P::Binds>,
+    pipeline_checked: bool,
+    extent_missing: Failing,
+}
+
+impl<P: ComputeDispatch> FromWorld for ComputeNode<P> {
+    fn from_world(world: &mut World) -> Self {
+        Self { params: BindGroupParams::new(world), pipeline_checked: false, extent_missing: default() }
+    }
+}
+
+impl<P: ComputeDispatch<Constants: PushConstants>> ComputeNode<P> {
+    /// Sets the pass's constants for the dispatches that follow.
+    /// Uses push constants when the device supports them, otherwise a uniform at the group after `Pass::Binds`.
+    pub fn set_push_constants(compute_pass: &mut ComputePass, world: &World, constants: &P::Constants) {
+        // the pipeline always has a slot since `P::Constants` has a range
+        if let Some(slot) = world.resource::<PipelineCompute<P>>().push_constants() {
+            slot.set_compute(compute_pass, world, constants);
+        }
+    }
+}
+
+impl<P: ComputeDispatch> ViewNode for ComputeNode<P> {
+
+    type ViewQuery = P::ViewQuery;
+
+    fn update(&mut self, world: &mut World) {
+        self.params.update(world);
+        check_pipeline::<P, PipelineCompute<P>>(&mut self.pipeline_checked, world);
+    }
+
+    fn run<'w>(
+        &self,
+        _: &mut RenderGraphContext,
+        context: &mut RenderContext<'w>,
+        view: QueryItem<'w, Self::ViewQuery>,
+        world: &'w World
+    ) -> Result<(), NodeRunError> {
+
+        let name = type_name::<P>();
+        let Some(binds) = P::bind_groups(&view, world) else {
+            return Ok(());
+        };
+
+        let pipelines = world.resource::<PipelineCache>();
+        let Some(compute_pipeline) = world.get_resource::<PipelineCompute<P>>() else {
+            return Ok(());
+        };
+        let Some(pipeline) = pipelines.get_compute_pipeline(compute_pipeline.id()) else {
+            // still compiling or failed, PipelineStatusPlugin<P> reports failures once instead of every frame
+            return Ok(());
+        };
+
+        let Some(groups) = self.params.create_or_report::<P>(&binds, compute_pipeline, context.render_device(), world) else {
+            return Ok(());
+        };
+
+        let dispatches = P::dispatches(&view, world);
+        if self.extent_missing.starts(dispatches.is_none()) {
+            warn!("Missing {name} GPU image for dispatch extent, skipping the pass until it's ready");
+        }
+        let Some(dispatches) = dispatches else {
+            return Ok(());
+        };
+
+        let descriptor = ComputePassDescriptor {
+            label: Some(name),
+            timestamp_writes: None,
+        };
+        let diagnostics = context.diagnostic_recorder();
+        let mut compute_pass = context.command_encoder().begin_compute_pass(&descriptor);
+        let pass_span = diagnostics.pass_span(&mut compute_pass, pass_name::<P>());
+        compute_pass.push_debug_group(pass_name::<P>());
+        compute_pass.set_pipeline(pipeline);
+        for (index, group) in groups.iter().enumerate() {
+            compute_pass.set_bind_group(index as u32, &group.bind_group, &group.offsets);
+        }
+        for (index, extent) in dispatches.into_iter().enumerate() {
+            let UVec3 { x, y, z } = P::workgroups(extent);
+            if x > 0 && y > 0 && z > 0 {
+                P::before_dispatch(&view, world, &mut compute_pass, index);
+                compute_pass.dispatch_workgroups(x, y, z);
+            }
+        }
+        compute_pass.pop_debug_group();
+        pass_span.end(&mut compute_pass);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failing_reports_once_per_outage() {
+        let failing = Failing::default();
+        assert!(!failing.starts(false));
+        assert!(failing.starts(true));
+        assert!(!failing.starts(true));
+        assert!(!failing.starts(false));
+        assert!(failing.starts(true));
+    }
+}
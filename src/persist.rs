@@ -0,0 +1,216 @@
+use std::{marker::*, path::{Path, PathBuf}, sync::{mpsc::*, Mutex}};
+use bevy::{app::*, asset::*, ecs::system::*, image::*, math::*, prelude::*, tasks::*};
+use bevy::render::{render_asset::*, render_resource::*, renderer::*, texture::GpuImage, *};
+use image::ImageReader;
+
+use crate::{attach::Attach, readback::*, texel::*};
+
+/// How many frames a decoded file waits for its attachment's GPU image before the load is given up.
+const UPLOAD_TIMEOUT_FRAMES: u32 = 60;
+
+/// Commands for persisting attachment contents between sessions.
+/// Float formats are written as EXR and 8-bit formats as PNG, see `FileFormat::for_texture`.
+pub trait AttachFileCommands {
+    /// Reads back attachment N of the entity's A and writes it to `path` on the IO task pool.
+    /// Requires `AttachFilePlugin<A, N>`.
+    fn save_attachment<A: Attach<N>, const N: usize>(&mut self, path: impl Into<PathBuf>) -> &mut Self;
+
+    /// Decodes `path` on the IO task pool and uploads it into attachment N of the entity's A on the GPU.
+    /// Files that don't match the attachment's size at upload time are cropped or zero-padded from the top-left.
+    /// Requires `AttachFilePlugin<A, N>`.
+    fn load_attachment<A: Attach<N>, const N: usize>(&mut self, path: impl Into<PathBuf>) -> &mut Self;
+}
+
+impl AttachFileCommands for EntityCommands<'_> {
+    fn save_attachment<A: Attach<N>, const N: usize>(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        let path = path.into();
+        let request = self.read_back::<A, N>(None);
+        self.observe(move |trigger: Trigger<AttachmentReadback<A, N>>, mut commands: Commands| {
+            if trigger.event().id != request {
+                return;
+            }
+            commands.entity(trigger.observer()).despawn();
+            let image = match &trigger.event().result {
+                Ok(image) => image,
+                Err(error) => {
+                    error!("Failed to read back attachment for saving: {error}");
+                    return;
+                }
+            };
+            let size = image.size();
+            let format = image.texture_descriptor.format;
+            let Some(data) = image.data.clone() else {
+                return;
+            };
+            let path = path.clone();
+            IoTaskPool::get().spawn(async move {
+                match save_texels(&data, size, format, &path) {
+                    Ok(()) => info!("Saved attachment to {path:?}"),
+                    Err(error) => error!("Failed to save attachment to {path:?}: {error}"),
+                }
+            }).detach();
+        })
+    }
+
+    fn load_attachment<A: Attach<N>, const N: usize>(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        let path = path.into();
+        self.queue(move |entity: EntityWorldMut| {
+            let Some(loads) = entity.world().get_resource::<FileLoads<A, N>>() else {
+                warn!("Cannot load {path:?}, AttachFilePlugin isn't added");
+                return;
+            };
+            let tx = loads.tx.clone();
+            let entity = entity.id();
+            IoTaskPool::get().spawn(async move {
+                match load_texels(&path, A::TEXTURE_FORMAT) {
+                    Ok((size, texels)) => { let _ = tx.send(DecodedFile { entity, path, size, texels }); }
+                    Err(error) => error!("Failed to load attachment from {path:?}: {error}"),
+                }
+            }).detach();
+        })
+    }
+}
+
+/// Adds what `AttachFileCommands` needs for attachment N of A.
+/// Saving goes through `ReadbackPlugin<A, N>`, which is added unless it already is.
+/// Loading writes decoded files straight into the GPU texture, the main-world `Image::data` stays `None`
+/// so resizes and copy-on-resize keep working on whatever was drawn since.
+pub struct AttachFilePlugin<A, const N: usize>(PhantomData<A>);
+
+impl<A, const N: usize> Default for AttachFilePlugin<A, N> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<A: Attach<N>, const N: usize> Plugin for AttachFilePlugin<A, N> {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ReadbackPlugin<A, N>>() {
+            app.add_plugins(ReadbackPlugin::<A, N>::default());
+        }
+        let (tx, rx) = channel();
+        app.insert_resource(FileLoads::<A, N> { tx, rx: Mutex::new(rx), marker: PhantomData });
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.insert_resource(FileUploads::<A, N> { uploads: vec![], marker: PhantomData });
+            render_app.add_systems(ExtractSchedule, extract_file_loads::<A, N>);
+            render_app.add_systems(Render, upload_files::<A, N>.in_set(RenderSet::PrepareResources));
+        }
+    }
+}
+
+struct DecodedFile {
+    entity: Entity,
+    path: PathBuf,
+    size: UVec2,
+    texels: Vec<u8>,
+}
+
+struct FileUpload {
+    file: DecodedFile,
+    /// Follows the attachment's current image, see `extract_file_loads`.
+    image: AssetId<Image>,
+    /// Frames spent waiting for the GPU image.
+    waited: u32,
+}
+
+/// Main-world end of the IO tasks decoding files, drained on extract.
+#[derive(Resource)]
+struct FileLoads<A, const N: usize> {
+    tx: Sender<DecodedFile>,
+    rx: Mutex<Receiver<DecodedFile>>,
+    marker: PhantomData<A>,
+}
+
+#[derive(Resource)]
+struct FileUploads<A, const N: usize> {
+    uploads: Vec<FileUpload>,
+    marker: PhantomData<A>,
+}
+
+/// Moves decoded files into the render world, pending uploads follow their attachment's handle like readbacks do.
+fn extract_file_loads<A: Attach<N>, const N: usize>(
+    mut uploads: ResMut<FileUploads<A, N>>,
+    loads: Extract<Res<FileLoads<A, N>>>,
+    attachments: Extract<Query<&A>>,
+) {
+    let uploads = &mut uploads.uploads;
+    uploads.extend(loads.rx.lock().unwrap().try_iter().map(|file| FileUpload { file, image: AssetId::default(), waited: 0 }));
+    uploads.retain_mut(|upload| match attachments.get(upload.file.entity) {
+        Ok(attach) => {
+            upload.image = attach[N].id();
+            true
+        }
+        Err(_) => {
+            warn!("Cannot load {:?}, entity has no attachment component", upload.file.path);
+            false
+        }
+    });
+}
+
+/// Writes each decoded file into its attachment's texture, cropped or padded to the texture's current size.
+/// Runs after `RenderSet::PrepareAssets`, so it lands on a texture recreated by a resize this frame.
+fn upload_files<A: Attach<N>, const N: usize>(
+    mut uploads: ResMut<FileUploads<A, N>>,
+    gpu_images: Res<RenderAssets<GpuImage>>,
+    queue: Res<RenderQueue>,
+) {
+    uploads.uploads.retain_mut(|FileUpload { file, image, waited }| {
+        let Some(gpu_image) = gpu_images.get(*image) else {
+            *waited += 1;
+            if *waited < UPLOAD_TIMEOUT_FRAMES {
+                return true;
+            }
+            error!("Failed to load attachment from {:?}, its image wasn't uploaded to the GPU within {waited} frames", file.path);
+            return false;
+        };
+        let size = UVec2::new(gpu_image.size.width, gpu_image.size.height);
+        let pixel_size = gpu_image.texture_format.pixel_size() as u32;
+        queue.write_texture(
+            gpu_image.texture.as_image_copy(),
+            &blit(&file.texels, file.size, size, pixel_size),
+            TexelCopyBufferLayout { offset: 0, bytes_per_row: Some(size.x * pixel_size), rows_per_image: None },
+            Extent3d { width: size.x, height: size.y, depth_or_array_layers: 1 },
+        );
+        info!("Loaded attachment from {:?}", file.path);
+        false
+    });
+}
+
+fn save_texels(data: &[u8], size: UVec2, format: TextureFormat, path: &Path) -> Result {
+    let file_format = FileFormat::for_texture(format)?;
+    texels_to_dynamic(data, size, format)?.save_with_format(path, file_format.image_format())?;
+    Ok(())
+}
+
+/// Decodes `path` into tightly packed texels of `format`, at the file's own size.
+fn load_texels(path: &Path, format: TextureFormat) -> Result<(UVec2, Vec<u8>)> {
+    let image = ImageReader::open(path)?.with_guessed_format()?.decode()?;
+    let size = UVec2::new(image.width(), image.height());
+    Ok((size, dynamic_to_texels(image, format)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn texels_round_trip_through_files() {
+        let size = UVec2::new(5, 3);
+        let rgba8 = (0..size.x * size.y * 4).map(|i| (i * 3) as u8).collect::<Vec<_>>();
+        let rgba32 = (0..size.x * size.y * 4).flat_map(|i| (i as f32 / 7.0 - 2.0).to_le_bytes()).collect::<Vec<_>>();
+        for (data, format) in [(rgba8, TextureFormat::Rgba8Unorm), (rgba32, TextureFormat::Rgba32Float)] {
+            let extension = FileFormat::for_texture(format).unwrap().extension();
+            let path = std::env::temp_dir().join(format!("bevy_micro_tools_{}_{format:?}.{extension}", std::process::id()));
+            save_texels(&data, size, format, &path).unwrap();
+            let loaded = load_texels(&path, format);
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(loaded.unwrap(), (size, data), "{format:?}");
+        }
+    }
+
+    #[test]
+    fn loading_a_missing_file_fails() {
+        let path = std::env::temp_dir().join(format!("bevy_micro_tools_{}_missing.png", std::process::id()));
+        assert!(load_texels(&path, TextureFormat::Rgba8Unorm).is_err());
+    }
+}
@@ -0,0 +1,309 @@
+use std::{error::Error, fmt, marker::*, sync::{atomic::*, mpsc::*, Mutex}};
+use bevy::{app::*, asset::*, ecs::{component::Tick, system::EntityCommands}, image::*, math::*, prelude::*};
+use bevy::render::{render_asset::*, render_resource::*, renderer::*, texture::GpuImage, *};
+
+use crate::{attach::Attach, texel::*};
+
+/// Requests a one-shot copy of attachment N of the camera's A back to the main world.
+/// Insert it on the camera entity, the result is triggered on that same entity as an `AttachmentReadback<A, N>`.
+/// The request removes itself once the result arrives, whether it succeeded or not, re-insert it to read back again.
+/// Re-inserting (or mutating) it while a readback is in flight queues another one, the request then stays
+/// until the result of the latest one arrives.
+/// To read back without touching this component, e.g. from a library, use `ReadbackCommands::read_back`.
+#[derive(Component)]
+pub struct ReadbackAttachment<A: Attach<N>, const N: usize> {
+    /// Optional sub-rect in texels, clamped to the attachment's size. `None` reads back the whole image.
+    pub rect: Option<URect>,
+    id: ReadbackId,
+    marker: PhantomData<A>,
+}
+
+impl<A: Attach<N>, const N: usize> Default for ReadbackAttachment<A, N> {
+    fn default() -> Self {
+        Self { rect: None, id: ReadbackId::next(), marker: PhantomData }
+    }
+}
+
+impl<A: Attach<N>, const N: usize> ReadbackAttachment<A, N> {
+    pub fn rect(rect: URect) -> Self {
+        Self { rect: Some(rect), ..default() }
+    }
+
+    /// Carried by the results of this request, mutating the rect keeps it while re-inserting gets a new one.
+    pub fn id(&self) -> ReadbackId {
+        self.id
+    }
+}
+
+/// Identifies a readback request, so observers can tell their own results apart from other requests on the entity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ReadbackId(u64);
+
+impl ReadbackId {
+    fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Readbacks that bypass the entity's `ReadbackAttachment<A, N>`, so they never replace a request of its own.
+pub trait ReadbackCommands {
+    /// Queues a one-shot readback of attachment N of the entity's A, or of a sub-rect of it.
+    /// Returns the id carried by its `AttachmentReadback`. Requires `ReadbackPlugin<A, N>`.
+    fn read_back<A: Attach<N>, const N: usize>(&mut self, rect: Option<URect>) -> ReadbackId;
+}
+
+impl ReadbackCommands for EntityCommands<'_> {
+    fn read_back<A: Attach<N>, const N: usize>(&mut self, rect: Option<URect>) -> ReadbackId {
+        let id = ReadbackId::next();
+        self.queue(move |entity: EntityWorldMut| {
+            let origin = RequestOrigin { entity: entity.id(), tick: None, id };
+            match entity.world().get_resource::<QueuedReadbacks<A, N>>() {
+                Some(queued) => queued.lock().unwrap().push((origin, rect)),
+                None => warn!("Cannot read back attachment {N} of {}, ReadbackPlugin isn't added", origin.entity),
+            }
+        });
+        id
+    }
+}
+
+/// Observer event carrying the read back contents of attachment N of A, triggered once for every request.
+#[derive(Event)]
+pub struct AttachmentReadback<A: Attach<N>, const N: usize> {
+    /// Id of the request this is the result of, see `ReadbackAttachment::id` and `ReadbackCommands::read_back`.
+    pub id: ReadbackId,
+    /// Tightly packed copy of the attachment (or its sub-rect) with the row padding stripped, or why there's none.
+    pub result: Result<Image, ReadbackError>,
+    /// The texel rect that was actually copied, after clamping. Empty if nothing was copied.
+    pub rect: URect,
+    marker: PhantomData<A>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReadbackError {
+    /// The requesting entity has no A to read back from.
+    MissingAttachment,
+    /// The requested rect doesn't overlap the attachment.
+    EmptyRect { rect: URect, size: UVec2 },
+    /// The attachment's format can't be copied texel by texel, e.g. it's block-compressed or `Depth24Plus`.
+    UnsupportedFormat(TextureFormat),
+    /// The attachment's image still wasn't on the GPU after this many frames.
+    Timeout { frames: u32 },
+    /// The copied buffer couldn't be mapped, e.g. because the device was lost.
+    Map(String),
+}
+
+impl fmt::Display for ReadbackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingAttachment => write!(f, "entity has no attachment component to read back"),
+            Self::EmptyRect { rect, size } => write!(f, "readback rect {rect:?} doesn't overlap the {size} attachment"),
+            Self::UnsupportedFormat(format) => write!(f, "attachment format {format:?} can't be read back texel by texel"),
+            Self::Timeout { frames } => write!(f, "attachment image wasn't uploaded to the GPU within {frames} frames"),
+            Self::Map(error) => write!(f, "failed to map readback buffer: {error}"),
+        }
+    }
+}
+
+impl Error for ReadbackError {}
+
+/// Adds the systems needed to service `ReadbackAttachment<A, N>` requests.
+/// Unlike bevy's `gpu_readback::Readback`, which copies the whole texture every frame while it's present,
+/// requests are one-shot and can copy just a sub-rect.
+pub struct ReadbackPlugin<A, const N: usize>(PhantomData<A>);
+
+impl<A, const N: usize> Default for ReadbackPlugin<A, N> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<A: Attach<N>, const N: usize> Plugin for ReadbackPlugin<A, N> {
+    fn build(&self, app: &mut App) {
+        let (tx, rx) = channel();
+        app.insert_resource(ReadbackReceiver::<A, N>(Mutex::new(rx), PhantomData));
+        app.insert_resource(QueuedReadbacks::<A, N>(Mutex::new(vec![]), PhantomData));
+        app.add_systems(PreUpdate, receive_readbacks::<A, N>);
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.insert_resource(ReadbackSender::<A, N> { tx, requests: vec![], marker: PhantomData });
+            render_app.add_systems(ExtractSchedule, extract_readbacks::<A, N>);
+            render_app.add_systems(Render, submit_readbacks::<A, N>.after(render_system).in_set(RenderSet::Render));
+        }
+    }
+}
+
+/// How many frames a request waits for its attachment's GPU image before failing with `ReadbackError::Timeout`.
+const READBACK_TIMEOUT_FRAMES: u32 = 60;
+
+/// Where a request came from, sent back along with its result.
+#[derive(Clone, Copy)]
+struct RequestOrigin {
+    entity: Entity,
+    /// When the request component last changed, tells the main world whether a newer request is waiting.
+    /// `None` for requests queued through `ReadbackCommands`, which have no component to remove.
+    tick: Option<Tick>,
+    id: ReadbackId,
+}
+
+struct ReadbackRequest {
+    origin: RequestOrigin,
+    /// Follows the attachment's current image, see `extract_readbacks`.
+    image: AssetId<Image>,
+    rect: Option<URect>,
+    /// Frames spent waiting for the GPU image.
+    waited: u32,
+}
+
+struct ReadbackData {
+    origin: RequestOrigin,
+    rect: URect,
+    result: Result<(TextureFormat, ReadbackLayout, Vec<u8>), ReadbackError>,
+}
+
+#[derive(Resource, Deref)]
+struct ReadbackReceiver<A, const N: usize>(#[deref] Mutex<Receiver<ReadbackData>>, PhantomData<A>);
+
+/// Main-world requests from `ReadbackCommands`, drained on extract.
+#[derive(Resource, Deref)]
+struct QueuedReadbacks<A, const N: usize>(#[deref] Mutex<Vec<(RequestOrigin, Option<URect>)>>, PhantomData<A>);
+
+#[derive(Resource)]
+struct ReadbackSender<A, const N: usize> {
+    tx: Sender<ReadbackData>,
+    requests: Vec<ReadbackRequest>,
+    marker: PhantomData<A>,
+}
+
+fn send_error(tx: &Sender<ReadbackData>, origin: RequestOrigin, error: ReadbackError) {
+    let _ = tx.send(ReadbackData { origin, rect: URect::EMPTY, result: Err(error) });
+}
+
+/// Picks up inserted requests, `Changed` extracts each one once and catches re-inserts of one still in flight.
+/// Requests queued through `ReadbackCommands` are drained alongside them.
+/// Requests on entities without A fail right away, since there's nothing to copy.
+/// Pending requests follow their attachment's handle, which a resize may have swapped, and fail if A was removed.
+fn extract_readbacks<A: Attach<N>, const N: usize>(
+    mut sender: ResMut<ReadbackSender<A, N>>,
+    query: Extract<Query<(Entity, Ref<ReadbackAttachment<A, N>>), Changed<ReadbackAttachment<A, N>>>>,
+    queued: Extract<Res<QueuedReadbacks<A, N>>>,
+    attachments: Extract<Query<&A>>,
+) {
+    let ReadbackSender { tx, requests, .. } = &mut *sender;
+    requests.retain_mut(|request| match attachments.get(request.origin.entity) {
+        Ok(attach) => {
+            request.image = attach[N].id();
+            true
+        }
+        Err(_) => {
+            send_error(tx, request.origin, ReadbackError::MissingAttachment);
+            false
+        }
+    });
+    let inserted = query.iter()
+        .map(|(entity, readback)| (RequestOrigin { entity, tick: Some(readback.last_changed()), id: readback.id }, readback.rect));
+    for (origin, rect) in inserted.chain(queued.lock().unwrap().drain(..)) {
+        match attachments.get(origin.entity) {
+            Ok(attach) => requests.push(ReadbackRequest { origin, image: attach[N].id(), rect, waited: 0 }),
+            Err(_) => send_error(tx, origin, ReadbackError::MissingAttachment),
+        }
+    }
+}
+
+/// Copies each requested attachment into a mappable buffer once the frame has been submitted.
+/// Requests whose GPU image isn't ready yet are kept around for the next frame, for up to `READBACK_TIMEOUT_FRAMES`.
+fn submit_readbacks<A: Attach<N>, const N: usize>(
+    mut sender: ResMut<ReadbackSender<A, N>>,
+    gpu_images: Res<RenderAssets<GpuImage>>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+) {
+    if sender.requests.is_empty() {
+        return;
+    }
+
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor { label: Some("attachment_readback") });
+    let mut copies = vec![];
+    let sender = &mut *sender;
+    sender.requests.retain_mut(|request| {
+        let Some(gpu_image) = gpu_images.get(request.image) else {
+            request.waited += 1;
+            if request.waited < READBACK_TIMEOUT_FRAMES {
+                return true;
+            }
+            send_error(&sender.tx, request.origin, ReadbackError::Timeout { frames: request.waited });
+            return false;
+        };
+        let rect = clamp_rect(request.rect, gpu_image.size);
+        if rect.is_empty() {
+            let size = UVec2::new(gpu_image.size.width, gpu_image.size.height);
+            let result = Err(ReadbackError::EmptyRect { rect: request.rect.unwrap_or(rect), size });
+            let _ = sender.tx.send(ReadbackData { origin: request.origin, rect, result });
+            return false;
+        }
+        let Some(layout) = ReadbackLayout::new(rect.size(), gpu_image.texture_format) else {
+            send_error(&sender.tx, request.origin, ReadbackError::UnsupportedFormat(gpu_image.texture_format));
+            return false;
+        };
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("attachment_readback_buffer"),
+            size: layout.buffer_size(),
+            usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        encoder.copy_texture_to_buffer(
+            TexelCopyTextureInfo {
+                origin: Origin3d { x: rect.min.x, y: rect.min.y, z: 0 },
+                ..gpu_image.texture.as_image_copy()
+            },
+            TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(layout.padded_bytes_per_row()),
+                    rows_per_image: None,
+                },
+            },
+            Extent3d { width: layout.width, height: layout.height, depth_or_array_layers: 1 },
+        );
+        copies.push((request.origin, rect, gpu_image.texture_format, layout, buffer));
+        false
+    });
+    if copies.is_empty() {
+        return;
+    }
+    queue.submit([encoder.finish()]);
+
+    for (origin, rect, format, layout, buffer) in copies {
+        let tx = sender.tx.clone();
+        let mapped = buffer.clone();
+        buffer.slice(..).map_async(MapMode::Read, move |result| {
+            let result = result
+                .map(|()| {
+                    let padded = mapped.slice(..).get_mapped_range().to_vec();
+                    mapped.unmap();
+                    (format, layout, padded)
+                })
+                .map_err(|error| ReadbackError::Map(error.to_string()));
+            let _ = tx.send(ReadbackData { origin, rect, result });
+        });
+    }
+}
+
+/// Turns finished readbacks into images and triggers them on the requesting entity, failed ones included.
+/// The request component is removed unless it changed since this readback was extracted, which means another one is queued.
+fn receive_readbacks<A: Attach<N>, const N: usize>(
+    mut commands: Commands,
+    receiver: Res<ReadbackReceiver<A, N>>,
+    requests: Query<Ref<ReadbackAttachment<A, N>>>,
+) {
+    for ReadbackData { origin: RequestOrigin { entity, tick, id }, rect, result } in receiver.lock().unwrap().try_iter() {
+        if tick.is_some_and(|tick| requests.get(entity).is_ok_and(|readback| readback.last_changed() == tick)) {
+            commands.entity(entity).try_remove::<ReadbackAttachment<A, N>>();
+        }
+        let result = result.map(|(format, layout, padded)| {
+            let size = Extent3d { width: layout.width, height: layout.height, depth_or_array_layers: 1 };
+            Image::new(size, TextureDimension::D2, layout.unpad(&padded), format, RenderAssetUsages::default())
+        });
+        commands.trigger_targets(AttachmentReadback::<A, N> { id, result, rect, marker: PhantomData }, entity);
+    }
+}
@@ -0,0 +1,165 @@
+use std::{any::*, collections::{HashMap, HashSet}, marker::PhantomData, sync::{mpsc::*, Mutex}};
+use bevy::prelude::*;
+use bevy::render::{render_resource::*, Render, RenderApp, RenderSet};
+
+use crate::{node::ViewRasterPipeline, wgputil::*};
+
+/// Where a pipeline of a pass is in the `PipelineCache`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PipelineStatus {
+    /// Waiting for its shaders to load.
+    Queued,
+    /// Compiling on a task.
+    Creating,
+    Ok,
+    /// Failed to compile, stays like this until one of its shaders is reloaded.
+    Err(String),
+}
+
+impl From<&CachedPipelineState> for PipelineStatus {
+    fn from(state: &CachedPipelineState) -> Self {
+        match state {
+            CachedPipelineState::Queued => Self::Queued,
+            CachedPipelineState::Creating(_) => Self::Creating,
+            CachedPipelineState::Ok(_) => Self::Ok,
+            CachedPipelineState::Err(error) => Self::Err(error.to_string()),
+        }
+    }
+}
+
+/// Sent in the main world when a pipeline of pass P fails to compile, and again with `PipelineStatus::Ok`
+/// once it compiles after a fix, e.g. when the shader is hot reloaded.
+#[derive(Event)]
+pub struct PipelineStatusChanged<P> {
+    /// Asset paths of the pipeline's shaders, shaders added by handle only are left out.
+    pub shaders: Vec<String>,
+    pub status: PipelineStatus,
+    marker: PhantomData<P>,
+}
+
+/// Tracks the `RasterPipeline<P>` or `PipelineCompute<P>` of pass P, along with its specialized variants.
+/// Compile errors are logged once with the pass name and shader paths, rather than the node skipping silently every frame.
+pub struct PipelineStatusPlugin<P>(PhantomData<P>);
+
+impl<P> Default for PipelineStatusPlugin<P> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<P: Pass + Send + Sync + 'static> Plugin for PipelineStatusPlugin<P> {
+    fn build(&self, app: &mut App) {
+        let (tx, rx) = channel();
+        app.add_event::<PipelineStatusChanged<P>>();
+        app.insert_resource(PipelineStatusReceiver::<P>(Mutex::new(rx)));
+        app.add_systems(PreUpdate, receive_pipeline_status::<P>);
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.insert_resource(PipelineStatuses::<P> { tx, statuses: default(), failed: default() });
+            render_app.add_systems(Render, track_pipeline_status::<P>.in_set(RenderSet::Cleanup));
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum PipelineId {
+    Render(CachedRenderPipelineId),
+    Compute(CachedComputePipelineId),
+}
+
+/// Last known status of each pipeline of P, in the render world.
+#[derive(Resource)]
+pub struct PipelineStatuses<P> {
+    tx: Sender<PipelineStatusChanged<P>>,
+    statuses: HashMap<PipelineId, PipelineStatus>,
+    /// Pipelines that failed and haven't compiled since, so a later `Ok` counts as a recovery.
+    failed: HashSet<PipelineId>,
+}
+
+impl<P> PipelineStatuses<P> {
+    /// Status of the unspecialized pipeline, or `None` before it's been tracked.
+    pub fn raster(&self, id: CachedRenderPipelineId) -> Option<&PipelineStatus> {
+        self.statuses.get(&PipelineId::Render(id))
+    }
+
+    pub fn compute(&self, id: CachedComputePipelineId) -> Option<&PipelineStatus> {
+        self.statuses.get(&PipelineId::Compute(id))
+    }
+
+    /// Whether any pipeline of P is currently failing.
+    pub fn has_errors(&self) -> bool {
+        !self.failed.is_empty()
+    }
+}
+
+#[derive(Resource)]
+struct PipelineStatusReceiver<P>(Mutex<Receiver<PipelineStatusChanged<P>>>);
+
+fn track_pipeline_status<P: Pass + Send + Sync + 'static>(
+    mut tracker: ResMut<PipelineStatuses<P>>,
+    pipeline_cache: Res<PipelineCache>,
+    raster_pipeline: Option<Res<RasterPipeline<P>>>,
+    compute_pipeline: Option<Res<PipelineCompute<P>>>,
+    views: Query<&ViewRasterPipeline<P>>,
+) {
+    let name = type_name::<P>();
+    let mut ids = vec![];
+    ids.extend(raster_pipeline.map(|pipeline| PipelineId::Render(pipeline.id())));
+    ids.extend(compute_pipeline.map(|pipeline| PipelineId::Compute(pipeline.id())));
+    ids.extend(views.iter().map(|view| PipelineId::Render(view.id)));
+
+    let tracker = &mut *tracker;
+    for id in ids {
+        let status = match id {
+            PipelineId::Render(id) => pipeline_cache.get_render_pipeline_state(id),
+            PipelineId::Compute(id) => pipeline_cache.get_compute_pipeline_state(id),
+        };
+        let status = PipelineStatus::from(status);
+        if tracker.statuses.get(&id) == Some(&status) {
+            continue;
+        }
+        tracker.statuses.insert(id, status.clone());
+
+        let recovered = status == PipelineStatus::Ok && tracker.failed.remove(&id);
+        let shaders = || shader_paths(&pipeline_cache, id);
+        match &status {
+            PipelineStatus::Err(error) => {
+                tracker.failed.insert(id);
+                let shaders = shaders();
+                error!("{name} pipeline failed to compile {shaders:?}: {error}");
+                let _ = tracker.tx.send(PipelineStatusChanged { shaders, status, marker: PhantomData });
+            }
+            PipelineStatus::Ok if recovered => {
+                let shaders = shaders();
+                info!("{name} pipeline compiled again {shaders:?}");
+                let _ = tracker.tx.send(PipelineStatusChanged { shaders, status, marker: PhantomData });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Only called once the cache has processed the pipeline, its descriptor isn't available before that.
+fn shader_paths(pipeline_cache: &PipelineCache, id: PipelineId) -> Vec<String> {
+    let shaders = match id {
+        PipelineId::Render(id) => {
+            let descriptor = pipeline_cache.get_render_pipeline_descriptor(id);
+            let fragment = descriptor.fragment.as_ref().map(|fragment| &fragment.shader);
+            [Some(&descriptor.vertex.shader), fragment].into_iter().flatten().collect()
+        }
+        PipelineId::Compute(id) => vec![&pipeline_cache.get_compute_pipeline_descriptor(id).shader],
+    };
+    let mut paths: Vec<String> = vec![];
+    for path in shaders.into_iter().filter_map(Handle::path).map(ToString::to_string) {
+        if !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+fn receive_pipeline_status<P: Pass + Send + Sync + 'static>(
+    receiver: Res<PipelineStatusReceiver<P>>,
+    mut events: EventWriter<PipelineStatusChanged<P>>,
+) {
+    events.write_batch(receiver.0.lock().unwrap().try_iter());
+}
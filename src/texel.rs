@@ -0,0 +1,399 @@
+use std::{error::Error, fmt};
+use bevy::{image::TextureFormatPixelInfo, math::*, render::render_resource::*};
+use image::{DynamicImage, GrayImage, ImageFormat, Rgba32FImage, RgbaImage};
+
+// pure-CPU texel utils, nothing in here touches the GPU
+
+/// wgpu requires each row of a texture-to-buffer copy to start on a multiple of this many bytes.
+pub const COPY_BYTES_PER_ROW_ALIGNMENT: u32 = 256;
+
+/// Row layout of a texture-to-buffer copy.
+/// wgpu requires each row in the buffer to start on a 256-byte boundary, so rows are padded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReadbackLayout {
+    pub width: u32,
+    pub height: u32,
+    pub pixel_size: u32,
+}
+
+impl ReadbackLayout {
+    /// `None` for formats that can't be copied texel by texel: block-compressed ones,
+    /// and depth formats like `Depth24Plus` whose copy size depends on the aspect.
+    pub fn new(size: UVec2, format: TextureFormat) -> Option<Self> {
+        if format.block_dimensions() != (1, 1) {
+            return None;
+        }
+        let pixel_size = format.block_copy_size(None)?;
+        Some(Self { width: size.x, height: size.y, pixel_size })
+    }
+
+    pub fn unpadded_bytes_per_row(&self) -> u32 {
+        self.width * self.pixel_size
+    }
+
+    pub fn padded_bytes_per_row(&self) -> u32 {
+        self.unpadded_bytes_per_row().div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT
+    }
+
+    pub fn buffer_size(&self) -> u64 {
+        self.padded_bytes_per_row() as u64 * self.height as u64
+    }
+
+    /// Strips the per-row padding from a mapped buffer, returning tightly packed texel data.
+    pub fn unpad(&self, padded: &[u8]) -> Vec<u8> {
+        let padded_row = self.padded_bytes_per_row() as usize;
+        let unpadded_row = self.unpadded_bytes_per_row() as usize;
+        let mut data = Vec::with_capacity(unpadded_row * self.height as usize);
+        for row in padded.chunks(padded_row).take(self.height as usize) {
+            data.extend_from_slice(&row[..unpadded_row]);
+        }
+        data
+    }
+}
+
+/// Clamps an optional requested rect to the bounds of a texture, `None` covers the whole texture.
+pub fn clamp_rect(rect: Option<URect>, size: Extent3d) -> URect {
+    let full = URect::new(0, 0, size.width, size.height);
+    rect.map_or(full, |rect| rect.intersect(full))
+}
+
+/// Copies the overlapping top-left region of `src` into a zeroed buffer of `dst_size`.
+/// Used to fit a loaded file into an attachment whose size doesn't match.
+pub fn blit(src: &[u8], src_size: UVec2, dst_size: UVec2, pixel_size: u32) -> Vec<u8> {
+    let src_row = (src_size.x * pixel_size) as usize;
+    let dst_row = (dst_size.x * pixel_size) as usize;
+    let copy_row = src_row.min(dst_row);
+    let mut dst = vec![0; dst_row * dst_size.y as usize];
+    for y in 0..src_size.y.min(dst_size.y) as usize {
+        dst[y * dst_row..][..copy_row].copy_from_slice(&src[y * src_row..][..copy_row]);
+    }
+    dst
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TexelError {
+    /// No file format or conversion exists for this texture format.
+    UnsupportedFormat(TextureFormat),
+    /// The texel data doesn't cover `width * height` pixels of the format.
+    SizeMismatch { expected: usize, actual: usize },
+}
+
+impl fmt::Display for TexelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedFormat(format) => write!(f, "unsupported texture format {format:?}"),
+            Self::SizeMismatch { expected, actual } => write!(f, "expected {expected} bytes of texel data, got {actual}"),
+        }
+    }
+}
+
+impl Error for TexelError {}
+
+/// File format used to persist a texture, float formats go to EXR and 8-bit formats to PNG.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FileFormat {
+    Png,
+    Exr,
+}
+
+impl FileFormat {
+    pub fn for_texture(format: TextureFormat) -> Result<Self, TexelError> {
+        match format {
+            TextureFormat::Rgba32Float | TextureFormat::Rgba16Float => Ok(Self::Exr),
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb
+            | TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb
+            | TextureFormat::R8Unorm => Ok(Self::Png),
+            _ => Err(TexelError::UnsupportedFormat(format)),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Exr => "exr",
+        }
+    }
+
+    pub fn image_format(&self) -> ImageFormat {
+        match self {
+            Self::Png => ImageFormat::Png,
+            Self::Exr => ImageFormat::OpenExr,
+        }
+    }
+}
+
+/// Converts tightly packed texel data (e.g. an unpadded readback) into an encodable image.
+pub fn texels_to_dynamic(data: &[u8], size: UVec2, format: TextureFormat) -> Result<DynamicImage, TexelError> {
+    FileFormat::for_texture(format)?;
+    let expected = (size.x * size.y) as usize * format.pixel_size();
+    if data.len() != expected {
+        return Err(TexelError::SizeMismatch { expected, actual: data.len() });
+    }
+    let UVec2 { x: width, y: height } = size;
+    let image = match format {
+        TextureFormat::Rgba32Float => {
+            let floats = data.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect();
+            DynamicImage::ImageRgba32F(Rgba32FImage::from_raw(width, height, floats).unwrap())
+        }
+        TextureFormat::Rgba16Float => {
+            let floats = data.chunks_exact(2).map(|b| f16_to_f32(u16::from_le_bytes([b[0], b[1]]))).collect();
+            DynamicImage::ImageRgba32F(Rgba32FImage::from_raw(width, height, floats).unwrap())
+        }
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => {
+            DynamicImage::ImageRgba8(RgbaImage::from_raw(width, height, data.to_vec()).unwrap())
+        }
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => {
+            DynamicImage::ImageRgba8(RgbaImage::from_raw(width, height, swap_red_blue(data)).unwrap())
+        }
+        TextureFormat::R8Unorm => {
+            DynamicImage::ImageLuma8(GrayImage::from_raw(width, height, data.to_vec()).unwrap())
+        }
+        _ => unreachable!(),
+    };
+    Ok(image)
+}
+
+/// Converts a decoded image into tightly packed texel data of the given format.
+pub fn dynamic_to_texels(image: DynamicImage, format: TextureFormat) -> Result<Vec<u8>, TexelError> {
+    let data = match format {
+        TextureFormat::Rgba32Float => image.into_rgba32f().into_raw().into_iter()
+            .flat_map(f32::to_le_bytes)
+            .collect(),
+        TextureFormat::Rgba16Float => image.into_rgba32f().into_raw().into_iter()
+            .flat_map(|float| f32_to_f16(float).to_le_bytes())
+            .collect(),
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => image.into_rgba8().into_raw(),
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => swap_red_blue(&image.into_rgba8().into_raw()),
+        TextureFormat::R8Unorm => image.into_luma8().into_raw(),
+        _ => return Err(TexelError::UnsupportedFormat(format)),
+    };
+    Ok(data)
+}
+
+/// Swizzles between RGBA and BGRA, which is its own inverse.
+fn swap_red_blue(data: &[u8]) -> Vec<u8> {
+    data.chunks_exact(4).flat_map(|p| [p[2], p[1], p[0], p[3]]).collect()
+}
+
+/// IEEE 754 half to single precision, exact for every input including subnormals, infinities and NaN.
+pub fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half >> 15) as u32) << 31;
+    let exponent = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x3ff) as u32;
+    let bits = match (exponent, mantissa) {
+        (0, 0) => sign,
+        (0, _) => {
+            let value = mantissa as f32 / (1 << 24) as f32;
+            return if sign == 0 { value } else { -value };
+        }
+        (0x1f, _) => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+    f32::from_bits(bits)
+}
+
+/// IEEE 754 single to half precision, rounding to nearest with ties to even and saturating to infinity on overflow.
+pub fn f32_to_f16(float: f32) -> u16 {
+    let bits = float.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa == 0 { 0 } else { 0x200 };
+    }
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        // subnormal half, or zero if it's too small to represent at all
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let half = (mantissa >> shift) as u16;
+        return sign | (half + round_up(mantissa, shift, half) as u16);
+    }
+    // a carry out of the mantissa bumps the exponent, up to infinity, which is the correctly rounded result
+    let half = sign | ((exponent as u16) << 10) | (mantissa >> 13) as u16;
+    half + round_up(mantissa, 13, half) as u16
+}
+
+/// Whether dropping the low `shift` bits of `mantissa` rounds `half` up: past halfway, or exactly halfway and odd.
+fn round_up(mantissa: u32, shift: u32, half: u16) -> bool {
+    let dropped = mantissa & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    dropped > halfway || (dropped == halfway && half & 1 == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn readback_rows_are_padded_to_256_bytes() {
+        let layout = |width, format| ReadbackLayout::new(UVec2::new(width, 3), format).unwrap();
+        assert_eq!(layout(1, TextureFormat::Rgba32Float).padded_bytes_per_row(), 256);
+        assert_eq!(layout(16, TextureFormat::Rgba32Float).padded_bytes_per_row(), 256);
+        assert_eq!(layout(17, TextureFormat::Rgba32Float).padded_bytes_per_row(), 512);
+        assert_eq!(layout(64, TextureFormat::Rgba8Unorm).padded_bytes_per_row(), 256);
+        assert_eq!(layout(65, TextureFormat::Rgba8Unorm).unpadded_bytes_per_row(), 260);
+        assert_eq!(layout(65, TextureFormat::Rgba8Unorm).padded_bytes_per_row(), 512);
+        assert_eq!(layout(65, TextureFormat::Rgba8Unorm).buffer_size(), 3 * 512);
+    }
+
+    #[test]
+    fn readback_layout_rejects_formats_without_a_texel_size() {
+        let layout = |format| ReadbackLayout::new(UVec2::new(4, 4), format);
+        assert_eq!(layout(TextureFormat::Depth32Float).map(|layout| layout.pixel_size), Some(4));
+        assert_eq!(layout(TextureFormat::Depth24Plus), None);
+        assert_eq!(layout(TextureFormat::Depth24PlusStencil8), None);
+        assert_eq!(layout(TextureFormat::Bc1RgbaUnorm), None);
+        assert_eq!(layout(TextureFormat::Astc { block: AstcBlock::B4x4, channel: AstcChannel::Unorm }), None);
+    }
+
+    #[test]
+    fn unpad_strips_row_padding_of_odd_widths() {
+        for (width, format) in [(3, TextureFormat::R8Unorm), (5, TextureFormat::Rgba8Unorm), (7, TextureFormat::Rgba16Float)] {
+            let layout = ReadbackLayout::new(UVec2::new(width, 3), format).unwrap();
+            let row = layout.unpadded_bytes_per_row() as usize;
+            let texels: Vec<u8> = (0..row * 3).map(|i| i as u8).collect();
+            let mut padded = vec![0xff; layout.buffer_size() as usize];
+            for (y, texel_row) in texels.chunks(row).enumerate() {
+                padded[y * layout.padded_bytes_per_row() as usize..][..row].copy_from_slice(texel_row);
+            }
+            assert_eq!(layout.unpad(&padded), texels, "{width} texels of {format:?}");
+        }
+    }
+
+    #[test]
+    fn clamp_rect_to_texture_edges() {
+        let size = Extent3d { width: 8, height: 6, depth_or_array_layers: 1 };
+        assert_eq!(clamp_rect(None, size), URect::new(0, 0, 8, 6));
+        assert_eq!(clamp_rect(Some(URect::new(2, 1, 4, 3)), size), URect::new(2, 1, 4, 3));
+        assert_eq!(clamp_rect(Some(URect::new(6, 4, 20, 20)), size), URect::new(6, 4, 8, 6));
+        assert_eq!(clamp_rect(Some(URect::new(0, 0, 8, 6)), size), URect::new(0, 0, 8, 6));
+        assert!(clamp_rect(Some(URect::new(8, 0, 10, 6)), size).is_empty());
+        assert!(clamp_rect(Some(URect::new(10, 10, 20, 20)), size).is_empty());
+    }
+
+    #[test]
+    fn blit_crops_and_zero_pads_from_the_top_left() {
+        let src = [1, 2, 3, 4, 5, 6];
+        assert_eq!(blit(&src, UVec2::new(3, 2), UVec2::new(2, 3), 1), [1, 2, 4, 5, 0, 0]);
+        assert_eq!(blit(&src, UVec2::new(3, 2), UVec2::new(4, 2), 1), [1, 2, 3, 0, 4, 5, 6, 0]);
+        assert_eq!(blit(&src, UVec2::new(3, 2), UVec2::new(3, 2), 1), src);
+        assert_eq!(blit(&src, UVec2::new(1, 3), UVec2::new(1, 1), 2), [1, 2]);
+        assert!(blit(&src, UVec2::new(3, 2), UVec2::new(0, 0), 1).is_empty());
+    }
+
+    #[test]
+    fn swap_red_blue_is_its_own_inverse() {
+        let rgba = [1, 2, 3, 4, 5, 6, 7, 8];
+        assert_eq!(swap_red_blue(&rgba), [3, 2, 1, 4, 7, 6, 5, 8]);
+        assert_eq!(swap_red_blue(&swap_red_blue(&rgba)), rgba);
+    }
+
+    #[test]
+    fn f16_round_trips_every_value() {
+        for half in 0..=u16::MAX {
+            let float = f16_to_f32(half);
+            if float.is_nan() {
+                assert!(f16_to_f32(f32_to_f16(float)).is_nan(), "{half:#06x}");
+            } else {
+                assert_eq!(f32_to_f16(float), half, "{half:#06x} -> {float}");
+            }
+        }
+    }
+
+    #[test]
+    fn f16_special_values() {
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x8001), -(2f32.powi(-24)));
+        assert_eq!(f16_to_f32(0x03ff), 1023.0 * 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x7bff), 65504.0);
+        assert_eq!(f16_to_f32(0x7c00), f32::INFINITY);
+        assert_eq!(f16_to_f32(0xfc00), f32::NEG_INFINITY);
+        assert!(f16_to_f32(0x7e00).is_nan());
+        assert_eq!(f16_to_f32(0x8000).to_bits(), (-0.0f32).to_bits());
+
+        assert_eq!(f32_to_f16(f32::INFINITY), 0x7c00);
+        assert_eq!(f32_to_f16(f32::NEG_INFINITY), 0xfc00);
+        assert_eq!(f32_to_f16(f32::NAN) & 0x7c00, 0x7c00);
+        assert_ne!(f32_to_f16(f32::NAN) & 0x3ff, 0);
+        assert_eq!(f32_to_f16(-0.0), 0x8000);
+    }
+
+    #[test]
+    fn f32_to_f16_overflows_to_infinity() {
+        assert_eq!(f32_to_f16(65504.0), 0x7bff);
+        assert_eq!(f32_to_f16(65519.0), 0x7bff);
+        assert_eq!(f32_to_f16(65520.0), 0x7c00);
+        assert_eq!(f32_to_f16(1e10), 0x7c00);
+        assert_eq!(f32_to_f16(-1e10), 0xfc00);
+        assert_eq!(f32_to_f16(f32::MAX), 0x7c00);
+    }
+
+    #[test]
+    fn f32_to_f16_rounds_ties_to_even() {
+        // halfway between 0x3c00 and 0x3c01 goes down to the even one, halfway between 0x3c01 and 0x3c02 goes up
+        assert_eq!(f32_to_f16(1.0 + 2f32.powi(-11)), 0x3c00);
+        assert_eq!(f32_to_f16(1.0 + 3.0 * 2f32.powi(-11)), 0x3c02);
+        assert_eq!(f32_to_f16(1.0 + 2f32.powi(-11) + 2f32.powi(-20)), 0x3c01);
+        // same for subnormals, where half the smallest subnormal rounds to zero
+        assert_eq!(f32_to_f16(2f32.powi(-25)), 0x0000);
+        assert_eq!(f32_to_f16(1.5 * 2f32.powi(-25)), 0x0001);
+        assert_eq!(f32_to_f16(3.0 * 2f32.powi(-25)), 0x0002);
+        assert_eq!(f32_to_f16(5.0 * 2f32.powi(-25)), 0x0002);
+        assert_eq!(f32_to_f16(2f32.powi(-26)), 0x0000);
+        assert_eq!(f32_to_f16(-(2f32.powi(-30))), 0x8000);
+        // the largest subnormal rounds up into the smallest normal
+        assert_eq!(f32_to_f16(1023.75 * 2f32.powi(-24)), 0x0400);
+    }
+
+    #[test]
+    fn texels_round_trip_through_dynamic_images() {
+        let size = UVec2::new(2, 1);
+        let rgba8 = [10, 20, 30, 40, 50, 60, 70, 80];
+        let halves: Vec<u8> = [1.0f32, 0.5, -2.0, 65504.0, 0.0, 2f32.powi(-24), 0.25, 1.0].into_iter()
+            .flat_map(|float| f32_to_f16(float).to_le_bytes())
+            .collect();
+        let floats: Vec<u8> = [1.0f32, 0.5, -2.0, 1e-30, 0.0, 123.456, 0.25, 1.0].into_iter()
+            .flat_map(f32::to_le_bytes)
+            .collect();
+        for (format, data) in [
+            (TextureFormat::Rgba8Unorm, &rgba8[..]),
+            (TextureFormat::Bgra8Unorm, &rgba8[..]),
+            (TextureFormat::Rgba16Float, &halves[..]),
+            (TextureFormat::Rgba32Float, &floats[..]),
+        ] {
+            let image = texels_to_dynamic(data, size, format).unwrap();
+            assert_eq!((image.width(), image.height()), (2, 1));
+            assert_eq!(dynamic_to_texels(image, format).unwrap(), data, "{format:?}");
+        }
+
+        let bgra = texels_to_dynamic(&rgba8, size, TextureFormat::Bgra8Unorm).unwrap();
+        assert_eq!(bgra.into_rgba8().into_raw(), [30, 20, 10, 40, 70, 60, 50, 80]);
+        let half = texels_to_dynamic(&halves, size, TextureFormat::Rgba16Float).unwrap();
+        assert_eq!(half.into_rgba32f().into_raw(), [1.0, 0.5, -2.0, 65504.0, 0.0, 2f32.powi(-24), 0.25, 1.0]);
+    }
+
+    #[test]
+    fn texel_conversion_errors() {
+        assert_eq!(
+            texels_to_dynamic(&[0; 7], UVec2::new(2, 1), TextureFormat::Rgba8Unorm).unwrap_err(),
+            TexelError::SizeMismatch { expected: 8, actual: 7 },
+        );
+        assert_eq!(
+            texels_to_dynamic(&[0; 8], UVec2::new(2, 1), TextureFormat::Rg32Uint).unwrap_err(),
+            TexelError::UnsupportedFormat(TextureFormat::Rg32Uint),
+        );
+        assert_eq!(
+            dynamic_to_texels(DynamicImage::new_rgba8(1, 1), TextureFormat::Depth32Float).unwrap_err(),
+            TexelError::UnsupportedFormat(TextureFormat::Depth32Float),
+        );
+        assert_eq!(FileFormat::for_texture(TextureFormat::Rgba32Float).unwrap().extension(), "exr");
+        assert_eq!(FileFormat::for_texture(TextureFormat::Bgra8UnormSrgb).unwrap().extension(), "png");
+    }
+}
@@ -0,0 +1,503 @@
+use std::{any::*, fmt, num::NonZeroU32};
+use bevy::{prelude::*, tasks::block_on};
+use bevy::render::{render_resource::*, renderer::RenderDevice};
+use naga::{valid::{Capabilities, ValidationFlags, Validator}, AddressSpace, ArraySize, ImageClass, ImageDimension, Module, ScalarKind, TypeInner};
+use naga_oil::compose::{Composer, NagaModuleDescriptor, ShaderDefValue};
+
+use crate::wgputil::{group_count, Binds, Pass, PushConstantSlot};
+
+/// Checks the `@group/@binding` declarations of a pass's shader against the layouts of its `Binds`,
+/// so a mismatch fails a test instead of a wgpu validation panic at pipeline creation.
+///
+/// Shaders are composed with naga_oil like the `PipelineCache` does, on the CPU only. `validate_entries` checks
+/// against plain layout entries without a GPU. Taking them from a pass's layouts needs a world with a `RenderDevice`
+/// and whatever `Pass::extra_layouts` reads, `fallback_render_device` provides a device on machines without a GPU:
+/// ```no_run
+/// # use bevy::prelude::*;
+/// # use bevy_micro_tools::{programs::draw::*, validate::*, wgputil::*, wgsl::*};
+/// let Some(device) = fallback_render_device() else { return };
+/// let mut world = World::new();
+/// world.insert_resource(device);
+/// world.init_resource::<UniformRing<DrawParams>>();
+/// let draw_params = Shader::from_wgsl(wgsl_module::<DrawParams>("bevy_micro_tools::draw_params"), "draw_params.wgsl");
+/// let shader = Shader::from_wgsl(include_str!("../programs/shaders/draw.wgsl"), DRAW_SHADER);
+/// BindingValidator::new().with_import(&draw_params).unwrap()
+///     .validate::<DrawCanvasPass>(&world, &shader, vec![]).unwrap();
+/// ```
+pub struct BindingValidator {
+    composer: Composer,
+}
+
+impl Default for BindingValidator {
+    fn default() -> Self {
+        Self { composer: Composer::default().with_capabilities(Capabilities::all()) }
+    }
+}
+
+impl BindingValidator {
+    pub fn new() -> Self {
+        default()
+    }
+
+    /// Makes `shader` available to `#import`, it needs a `#define_import_path` or is imported by its asset path.
+    pub fn with_import(mut self, shader: &Shader) -> Result<Self, BindingValidationError> {
+        if let Err(error) = self.composer.add_composable_module(shader.into()) {
+            return Err(BindingValidationError::Compose { pass: None, error: error.emit_to_string(&self.composer) });
+        }
+        Ok(self)
+    }
+
+    /// Composes `shader` with the defs the pipeline of P would get, plus `shader_defs` for ones the pass
+    /// adds elsewhere (e.g. `Compute::workgroup_shader_defs`), then validates it with `validate_module`.
+    /// The `Pass::extra_layout_entries` are checked at the groups right after the `Binds`, followed by the push constant fallback.
+    pub fn validate<P: Pass>(
+        &mut self,
+        world: &World,
+        shader: &Shader,
+        shader_defs: Vec<ShaderDefVal>,
+    ) -> Result<(), BindingValidationError> {
+        let pass = type_name::<P>();
+        let device = world.resource::<RenderDevice>();
+        let mut groups = P::Binds::layout_entries(device);
+        let extra = P::extra_layout_entries();
+        let extra_layouts = P::extra_layouts(world).len();
+        assert_eq!(extra.len(), extra_layouts, "{pass} has {extra_layouts} extra layouts but entries for {}", extra.len());
+        let first_extra = group_count(&P::Binds::groups());
+        groups.extend(extra.into_iter().enumerate().map(|(index, entries)| ((first_extra + index) as u32, entries)));
+        let group = first_extra + extra_layouts;
+        let push_constants = PushConstantSlot::new::<P>(device, group);
+        groups.extend(push_constants.iter().flat_map(PushConstantSlot::layout_entries));
+
+        let mut defs = P::shader_defs();
+        defs.extend(shader_defs);
+        defs.extend(push_constants.iter().flat_map(PushConstantSlot::shader_defs));
+        self.validate_entries(pass, shader, defs, &groups)
+    }
+
+    /// Composes `shader` with `shader_defs` and validates it against `groups` given as plain layout entries,
+    /// without a `RenderDevice`. `validate` goes through this with the entries of a pass's layouts.
+    pub fn validate_entries(
+        &mut self,
+        pass: &'static str,
+        shader: &Shader,
+        mut shader_defs: Vec<ShaderDefVal>,
+        groups: &[(u32, Vec<BindGroupLayoutEntry>)],
+    ) -> Result<(), BindingValidationError> {
+        shader_defs.extend(shader.shader_defs.iter().cloned());
+        let module = self.composer.make_naga_module(NagaModuleDescriptor {
+            shader_defs: shader_defs.into_iter().map(shader_def_value).collect(),
+            ..NagaModuleDescriptor::from(shader)
+        });
+        let module = module
+            .map_err(|error| BindingValidationError::Compose { pass: Some(pass), error: error.emit_to_string(&self.composer) })?;
+        validate_module(pass, &module, groups)
+    }
+}
+
+fn shader_def_value(def: ShaderDefVal) -> (String, ShaderDefValue) {
+    match def {
+        ShaderDefVal::Bool(name, value) => (name, ShaderDefValue::Bool(value)),
+        ShaderDefVal::Int(name, value) => (name, ShaderDefValue::Int(value)),
+        ShaderDefVal::UInt(name, value) => (name, ShaderDefValue::UInt(value)),
+    }
+}
+
+/// Compares every resource binding declared in `module` against its entry in `groups`, given as `(group, entries)`.
+/// Groups missing from `groups` aren't checked, entries the shader doesn't declare are allowed like wgpu allows them.
+/// Visibility is checked against the stages whose entry points actually use the binding.
+pub fn validate_module(
+    pass: &'static str,
+    module: &Module,
+    groups: &[(u32, Vec<BindGroupLayoutEntry>)],
+) -> Result<(), BindingValidationError> {
+
+    let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(module)
+        .map_err(|error| BindingValidationError::Naga { pass, error: error.to_string() })?;
+
+    let mut mismatches = vec![];
+    for (handle, variable) in module.global_variables.iter() {
+        let Some(binding) = &variable.binding else { continue };
+        let Some((_, entries)) = groups.iter().find(|(group, _)| *group == binding.group) else { continue };
+        let mismatch = |problem| BindingMismatch {
+            pass,
+            group: binding.group,
+            binding: binding.binding,
+            name: variable.name.clone(),
+            problem,
+        };
+        let Some(entry) = entries.iter().find(|entry| entry.binding == binding.binding) else {
+            mismatches.push(mismatch(BindingProblem::Missing));
+            continue;
+        };
+
+        let mut inner = &module.types[variable.ty].inner;
+        let mut count = None;
+        if let TypeInner::BindingArray { base, size } = inner {
+            inner = &module.types[*base].inner;
+            count = Some(*size);
+        }
+        if let Some(problem) = binding_type_problem(variable.space, inner, &entry.ty) {
+            mismatches.push(mismatch(problem));
+            continue;
+        }
+        if let Some(problem) = count_problem(count, entry.count) {
+            mismatches.push(mismatch(problem));
+        }
+
+        let used = module.entry_points.iter().enumerate()
+            .filter(|(index, _)| !info.get_entry_point(*index)[handle].is_empty())
+            .fold(ShaderStages::NONE, |stages, (_, entry_point)| stages | match entry_point.stage {
+                ShaderStage::Vertex => ShaderStages::VERTEX,
+                ShaderStage::Fragment => ShaderStages::FRAGMENT,
+                ShaderStage::Compute => ShaderStages::COMPUTE,
+            });
+        if !entry.visibility.contains(used) {
+            mismatches.push(mismatch(BindingProblem::Visibility { used, layout: entry.visibility }));
+        }
+    }
+
+    match mismatches.is_empty() {
+        true => Ok(()),
+        false => Err(BindingValidationError::Mismatch(mismatches)),
+    }
+}
+
+fn binding_type_problem(space: AddressSpace, inner: &TypeInner, layout: &BindingType) -> Option<BindingProblem> {
+    let type_problem = || Some(BindingProblem::Type {
+        shader: ResourceKind::of_shader(space, inner),
+        layout: ResourceKind::of_layout(layout),
+    });
+    match (space, inner, layout) {
+        (AddressSpace::Uniform, _, BindingType::Buffer { ty: BufferBindingType::Uniform, .. }) => None,
+        (AddressSpace::Storage { access }, _, BindingType::Buffer { ty: BufferBindingType::Storage { read_only }, .. }) => {
+            let shader = !access.contains(naga::StorageAccess::STORE);
+            (shader != *read_only).then_some(BindingProblem::ReadOnly { shader, layout: *read_only })
+        }
+        (AddressSpace::Handle, TypeInner::Sampler { comparison }, BindingType::Sampler(sampler)) => {
+            (*comparison != (*sampler == SamplerBindingType::Comparison)).then(type_problem).flatten()
+        }
+        (AddressSpace::Handle, TypeInner::Image { dim, arrayed, class }, BindingType::Texture { sample_type, view_dimension, multisampled }) => {
+            let (shader_sample_type, multi) = match class {
+                ImageClass::Sampled { kind, multi } => (Some(*kind), *multi),
+                ImageClass::Depth { multi } => (None, *multi),
+                ImageClass::Storage { .. } => return type_problem(),
+            };
+            let sample_type_matches = matches!(
+                (shader_sample_type, sample_type),
+                (Some(ScalarKind::Float), TextureSampleType::Float { .. } | TextureSampleType::Depth)
+                    | (Some(ScalarKind::Sint), TextureSampleType::Sint)
+                    | (Some(ScalarKind::Uint), TextureSampleType::Uint)
+                    | (None, TextureSampleType::Depth)
+            );
+            if !sample_type_matches {
+                let shader = shader_sample_type.map_or("depth".into(), |kind| format!("{kind:?}"));
+                return Some(BindingProblem::SampleType { shader, layout: *sample_type });
+            }
+            dimension_problem(*dim, *arrayed, *view_dimension)
+                .or_else(|| (multi != *multisampled).then_some(BindingProblem::Multisampled { shader: multi, layout: *multisampled }))
+        }
+        (AddressSpace::Handle, TypeInner::Image { dim, arrayed, class: ImageClass::Storage { access, .. } }, BindingType::StorageTexture { access: layout, view_dimension, .. }) => {
+            let shader = match (access.contains(naga::StorageAccess::LOAD), access.contains(naga::StorageAccess::STORE)) {
+                _ if access.contains(naga::StorageAccess::ATOMIC) => StorageTextureAccess::Atomic,
+                (true, true) => StorageTextureAccess::ReadWrite,
+                (true, false) => StorageTextureAccess::ReadOnly,
+                _ => StorageTextureAccess::WriteOnly,
+            };
+            if shader != *layout {
+                return Some(BindingProblem::StorageAccess { shader, layout: *layout });
+            }
+            dimension_problem(*dim, *arrayed, *view_dimension)
+        }
+        _ => type_problem(),
+    }
+}
+
+fn dimension_problem(dim: ImageDimension, arrayed: bool, layout: TextureViewDimension) -> Option<BindingProblem> {
+    let shader = match (dim, arrayed) {
+        (ImageDimension::D1, _) => TextureViewDimension::D1,
+        (ImageDimension::D2, false) => TextureViewDimension::D2,
+        (ImageDimension::D2, true) => TextureViewDimension::D2Array,
+        (ImageDimension::D3, _) => TextureViewDimension::D3,
+        (ImageDimension::Cube, false) => TextureViewDimension::Cube,
+        (ImageDimension::Cube, true) => TextureViewDimension::CubeArray,
+    };
+    (shader != layout).then_some(BindingProblem::Dimension { shader, layout })
+}
+
+fn count_problem(size: Option<ArraySize>, layout: Option<NonZeroU32>) -> Option<BindingProblem> {
+    let matches = match (size, layout) {
+        (None, None) | (Some(ArraySize::Dynamic), Some(_)) => true,
+        (Some(ArraySize::Constant(shader)), Some(layout)) => shader == layout,
+        _ => false,
+    };
+    let shader = match size {
+        Some(ArraySize::Constant(size)) => Some(size.get()),
+        _ => None,
+    };
+    (!matches).then_some(BindingProblem::Count { shader, array: size.is_some(), layout })
+}
+
+/// A device on wgpu's fallback adapter, i.e. a software rasterizer like lavapipe or WARP when one is installed,
+/// or on any adapter if there's none. Enough to build layouts for `BindingValidator` in tests on machines without a GPU.
+pub fn fallback_render_device() -> Option<RenderDevice> {
+    let instance = wgpu::Instance::new(&default());
+    let fallback = wgpu::RequestAdapterOptions { force_fallback_adapter: true, ..default() };
+    let adapter = block_on(instance.request_adapter(&fallback))
+        .or_else(|| block_on(instance.request_adapter(&default())))?;
+    let descriptor = wgpu::DeviceDescriptor {
+        label: Some("binding_validation_device"),
+        required_features: adapter.features(),
+        required_limits: adapter.limits(),
+        ..default()
+    };
+    let (device, _) = block_on(adapter.request_device(&descriptor, None)).ok()?;
+    Some(device.into())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindingValidationError {
+    /// naga_oil failed to compose the shader, e.g. an unknown import or an undefined shader def.
+    Compose { pass: Option<&'static str>, error: String },
+    /// naga rejected the composed module.
+    Naga { pass: &'static str, error: String },
+    /// Bindings that don't match the pass's layouts, all of them rather than just the first.
+    Mismatch(Vec<BindingMismatch>),
+}
+
+impl fmt::Display for BindingValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Compose { pass: Some(pass), error } => write!(f, "failed to compose {pass} shader: {error}"),
+            Self::Compose { pass: None, error } => write!(f, "failed to compose shader import: {error}"),
+            Self::Naga { pass, error } => write!(f, "{pass} shader failed validation: {error}"),
+            Self::Mismatch(mismatches) => {
+                for (index, mismatch) in mismatches.iter().enumerate() {
+                    if index > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{mismatch}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for BindingValidationError {}
+
+/// A shader binding that doesn't match its layout entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BindingMismatch {
+    pub pass: &'static str,
+    pub group: u32,
+    pub binding: u32,
+    /// Name of the variable in the shader.
+    pub name: Option<String>,
+    pub problem: BindingProblem,
+}
+
+impl fmt::Display for BindingMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { pass, group, binding, name, problem } = self;
+        write!(f, "{pass} @group({group}) @binding({binding})")?;
+        if let Some(name) = name {
+            write!(f, " `{name}`")?;
+        }
+        write!(f, ": {problem}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindingProblem {
+    /// The shader declares a binding the group's layout has no entry for.
+    Missing,
+    /// Different kinds of resource, e.g. a texture in the shader and a buffer in the layout.
+    Type { shader: ResourceKind, layout: ResourceKind },
+    /// The shader's scalar kind (or `depth`) doesn't fit the layout's sample type.
+    SampleType { shader: String, layout: TextureSampleType },
+    Dimension { shader: TextureViewDimension, layout: TextureViewDimension },
+    Multisampled { shader: bool, layout: bool },
+    /// A storage buffer is read-only on one side and read-write on the other.
+    ReadOnly { shader: bool, layout: bool },
+    StorageAccess { shader: StorageTextureAccess, layout: StorageTextureAccess },
+    /// Binding array size in the shader, `None` for runtime-sized, against the layout's `count`.
+    Count { shader: Option<u32>, array: bool, layout: Option<NonZeroU32> },
+    /// Stages using the binding that the layout entry isn't visible to.
+    Visibility { used: ShaderStages, layout: ShaderStages },
+}
+
+impl fmt::Display for BindingProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => write!(f, "no entry in the layout"),
+            Self::Type { shader, layout } => write!(f, "shader declares a {shader} but the layout has a {layout}"),
+            Self::SampleType { shader, layout } => write!(f, "shader samples {shader} but the layout has {layout:?}"),
+            Self::Dimension { shader, layout } => write!(f, "shader declares {shader:?} but the layout has {layout:?}"),
+            Self::Multisampled { shader, layout } => write!(f, "shader multisampled is {shader} but the layout's is {layout}"),
+            Self::ReadOnly { shader, layout } => write!(f, "shader read-only is {shader} but the layout's is {layout}"),
+            Self::StorageAccess { shader, layout } => write!(f, "shader access is {shader:?} but the layout's is {layout:?}"),
+            Self::Count { shader: _, array: false, layout } => write!(f, "shader declares a single binding but the layout has count {layout:?}"),
+            Self::Count { shader, array: true, layout: None } => write!(f, "shader declares a binding array of {shader:?} but the layout has no count"),
+            Self::Count { shader, array: true, layout: Some(layout) } => write!(f, "shader declares a binding array of {shader:?} but the layout has count {layout}"),
+            Self::Visibility { used, layout } => write!(f, "used in {used:?} but only visible to {layout:?}"),
+        }
+    }
+}
+
+/// Coarse kind of a binding, to report type mismatches on both sides the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceKind {
+    UniformBuffer,
+    StorageBuffer,
+    Sampler,
+    ComparisonSampler,
+    Texture,
+    DepthTexture,
+    StorageTexture,
+    AccelerationStructure,
+    /// Anything else, e.g. a variable in the private or workgroup address space given a binding.
+    Other,
+}
+
+impl ResourceKind {
+    fn of_shader(space: AddressSpace, inner: &TypeInner) -> Self {
+        match (space, inner) {
+            (AddressSpace::Uniform, _) => Self::UniformBuffer,
+            (AddressSpace::Storage { .. }, _) => Self::StorageBuffer,
+            (AddressSpace::Handle, TypeInner::Sampler { comparison: false }) => Self::Sampler,
+            (AddressSpace::Handle, TypeInner::Sampler { comparison: true }) => Self::ComparisonSampler,
+            (AddressSpace::Handle, TypeInner::Image { class: ImageClass::Sampled { .. }, .. }) => Self::Texture,
+            (AddressSpace::Handle, TypeInner::Image { class: ImageClass::Depth { .. }, .. }) => Self::DepthTexture,
+            (AddressSpace::Handle, TypeInner::Image { class: ImageClass::Storage { .. }, .. }) => Self::StorageTexture,
+            (AddressSpace::Handle, TypeInner::AccelerationStructure) => Self::AccelerationStructure,
+            _ => Self::Other,
+        }
+    }
+
+    fn of_layout(ty: &BindingType) -> Self {
+        match ty {
+            BindingType::Buffer { ty: BufferBindingType::Uniform, .. } => Self::UniformBuffer,
+            BindingType::Buffer { ty: BufferBindingType::Storage { .. }, .. } => Self::StorageBuffer,
+            BindingType::Sampler(SamplerBindingType::Comparison) => Self::ComparisonSampler,
+            BindingType::Sampler(_) => Self::Sampler,
+            BindingType::Texture { sample_type: TextureSampleType::Depth, .. } => Self::DepthTexture,
+            BindingType::Texture { .. } => Self::Texture,
+            BindingType::StorageTexture { .. } => Self::StorageTexture,
+            BindingType::AccelerationStructure => Self::AccelerationStructure,
+        }
+    }
+}
+
+impl fmt::Display for ResourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::UniformBuffer => "uniform buffer",
+            Self::StorageBuffer => "storage buffer",
+            Self::Sampler => "sampler",
+            Self::ComparisonSampler => "comparison sampler",
+            Self::Texture => "texture",
+            Self::DepthTexture => "depth texture",
+            Self::StorageTexture => "storage texture",
+            Self::AccelerationStructure => "acceleration structure",
+            Self::Other => "non-resource variable",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Stand-in for bevy's fullscreen vertex shader import, only the struct passthrough.wgsl uses.
+#[cfg(test)]
+pub(crate) const FULLSCREEN_STAND_IN: &str = "
+    #define_import_path bevy_core_pipeline::fullscreen_vertex_shader
+    struct FullscreenVertexOutput {
+        @builtin(position) position: vec4<f32>,
+        @location(0) uv: vec2<f32>,
+    };
+";
+
+#[cfg(test)]
+mod tests {
+    use bevy::render::render_resource::binding_types::*;
+    use super::*;
+
+    const TEXTURE_SHADER: &str = "
+        @group(0) @binding(0) var color: texture_2d<f32>;
+
+        @fragment
+        fn fragment(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+            return textureLoad(color, vec2<u32>(position.xy), 0);
+        }
+    ";
+
+    fn validate(source: &str, groups: &[(u32, Vec<BindGroupLayoutEntry>)]) -> Result<(), BindingValidationError> {
+        let shader = Shader::from_wgsl(source.to_string(), "test.wgsl");
+        BindingValidator::new().validate_entries("TestPass", &shader, vec![], groups)
+    }
+
+    fn entry(binding: u32, visibility: ShaderStages, ty: impl IntoBindGroupLayoutEntryBuilder) -> BindGroupLayoutEntry {
+        ty.into_bind_group_layout_entry_builder().build(binding, visibility)
+    }
+
+    fn problems(result: Result<(), BindingValidationError>) -> Vec<BindingProblem> {
+        match result {
+            Err(BindingValidationError::Mismatch(mismatches)) => mismatches.into_iter().map(|mismatch| mismatch.problem).collect(),
+            other => panic!("expected a mismatch, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn wrong_sample_type() {
+        let uint = entry(0, ShaderStages::FRAGMENT, texture_2d(TextureSampleType::Uint));
+        assert_eq!(
+            problems(validate(TEXTURE_SHADER, &[(0, vec![uint])])),
+            vec![BindingProblem::SampleType { shader: "Float".into(), layout: TextureSampleType::Uint }],
+        );
+    }
+
+    #[test]
+    fn view_dimension_mismatch() {
+        let array = entry(0, ShaderStages::FRAGMENT, texture_2d_array(TextureSampleType::Float { filterable: true }));
+        assert_eq!(
+            problems(validate(TEXTURE_SHADER, &[(0, vec![array])])),
+            vec![BindingProblem::Dimension { shader: TextureViewDimension::D2, layout: TextureViewDimension::D2Array }],
+        );
+    }
+
+    #[test]
+    fn binding_count_mismatch() {
+        let source = "
+            @group(0) @binding(0) var colors: binding_array<texture_2d<f32>, 4>;
+
+            @fragment
+            fn fragment(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+                return textureLoad(colors[0], vec2<u32>(position.xy), 0);
+            }
+        ";
+        let mut colors = entry(0, ShaderStages::FRAGMENT, texture_2d(TextureSampleType::Float { filterable: true }));
+        colors.count = NonZeroU32::new(2);
+        assert_eq!(
+            problems(validate(source, &[(0, vec![colors])])),
+            vec![BindingProblem::Count { shader: Some(4), array: true, layout: NonZeroU32::new(2) }],
+        );
+        colors.count = NonZeroU32::new(4);
+        assert_eq!(validate(source, &[(0, vec![colors])]), Ok(()));
+    }
+
+    #[test]
+    fn missing_visibility_stage() {
+        let vertex_only = entry(0, ShaderStages::VERTEX, texture_2d(TextureSampleType::Float { filterable: true }));
+        assert_eq!(
+            problems(validate(TEXTURE_SHADER, &[(0, vec![vertex_only])])),
+            vec![BindingProblem::Visibility { used: ShaderStages::FRAGMENT, layout: ShaderStages::VERTEX }],
+        );
+    }
+
+    #[test]
+    fn binding_absent_from_layout() {
+        let other = entry(1, ShaderStages::FRAGMENT, texture_2d(TextureSampleType::Float { filterable: true }));
+        assert_eq!(problems(validate(TEXTURE_SHADER, &[(0, vec![other])])), vec![BindingProblem::Missing]);
+        // groups left out entirely aren't checked
+        assert_eq!(validate(TEXTURE_SHADER, &[]), Ok(()));
+    }
+}
@@ -1,9 +1,10 @@
-use std::any::*;
+use std::{any::*, collections::HashMap, marker::PhantomData, num::NonZeroU64, sync::{Arc, Mutex}};
 use bevy::render::render_asset::RenderAssets;
-use bevy::render::storage::GpuShaderStorageBuffer;
-use bevy::render::texture::{FallbackImage, GpuImage};
-use bevy::{asset::*, log::*, prelude::*};
-use bevy::render::{render_resource::*, renderer::*};
+use bevy::render::storage::{GpuShaderStorageBuffer, ShaderStorageBuffer};
+use bevy::core_pipeline::fullscreen_vertex_shader::{fullscreen_shader_vertex_state, FULLSCREEN_SHADER_HANDLE};
+use bevy::{asset::*, diagnostic::FrameCount, ecs::system::{lifetimeless::SRes, ReadOnlySystemParam, SystemParamItem}, image::*, log::*, prelude::*};
+use bevy::render::{extract_component::ExtractComponent, render_phase::TrackedRenderPass, render_resource::{binding_types::{storage_buffer_read_only_sized, storage_buffer_sized, uniform_buffer, uniform_buffer_sized}, *}, renderer::*};
+use bevy::render::{view::{ViewUniform, ViewUniformOffset, ViewUniforms}, ExtractSchedule, Render, RenderApp, RenderSet};
 use derive_builder::Builder;
 use encase::internal::WriteInto;
 
@@ -23,7 +24,20 @@ pub struct ImageView<'a> {
 
 impl<'a> ImageViewBuilder<'a> {
     pub fn descriptor(self) -> TextureViewDescriptor<'a> {
-        let view_mode = self.build().unwrap();
+        self.build().unwrap().into()
+    }
+
+    /// Like `descriptor`, but checks the view against the texture it will be created on,
+    /// so invalid combinations surface as an `ImageViewError` instead of a wgpu validation panic.
+    pub fn try_descriptor(self, texture: &TextureDescriptor) -> Result<TextureViewDescriptor<'a>, ImageViewError> {
+        let view = self.build().map_err(|error| ImageViewError::Builder(error.to_string()))?.into();
+        validate_view(&view, texture)?;
+        Ok(view)
+    }
+}
+
+impl<'a> From<ImageView<'a>> for TextureViewDescriptor<'a> {
+    fn from(view_mode: ImageView<'a>) -> Self {
         TextureViewDescriptor {
             label: view_mode.label,
             format: view_mode.format,
@@ -38,6 +52,297 @@ impl<'a> ImageViewBuilder<'a> {
     }
 }
 
+#[derive(Builder)]
+#[builder(default, pattern = "owned")] 
+pub struct ImageTexture<'a> {
+    pub label: Option<&'a str>,
+    pub size: Extent3d,
+    pub mip_level_count: u32,
+    pub sample_count: u32,
+    pub dimension: TextureDimension,
+    pub format: TextureFormat,
+    pub usage: TextureUsages,
+    pub view_formats: &'a [TextureFormat],
+    pub view: Option<TextureViewDescriptor<'a>>,
+}
+
+impl Default for ImageTexture<'_> {
+    fn default() -> Self {
+        Self {
+            label: None,
+            size: Extent3d::default(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::bevy_default(),
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+            view: None,
+        }
+    }
+}
+
+impl<'a> ImageTextureBuilder<'a> {
+    pub fn descriptor(self) -> TextureDescriptor<'a> {
+        self.build().unwrap().into()
+    }
+}
+
+impl ImageTextureBuilder<'static> {
+    /// An `Image` with no CPU data, ready to be added to `Assets<Image>` and created on the GPU.
+    pub fn image(self) -> Image {
+        let texture = self.build().unwrap();
+        Image {
+            data: None,
+            texture_view_descriptor: texture.view.clone(),
+            texture_descriptor: texture.into(),
+            ..default()
+        }
+    }
+}
+
+impl<'a> From<ImageTexture<'a>> for TextureDescriptor<'a> {
+    fn from(texture: ImageTexture<'a>) -> Self {
+        TextureDescriptor {
+            label: texture.label,
+            size: texture.size,
+            mip_level_count: texture.mip_level_count,
+            sample_count: texture.sample_count,
+            dimension: texture.dimension,
+            format: texture.format,
+            usage: texture.usage,
+            view_formats: texture.view_formats,
+        }
+    }
+}
+
+#[derive(Builder)]
+#[builder(default, pattern = "owned")] 
+pub struct TextureSampler<'a> {
+    pub label: Option<&'a str>,
+    pub address_mode_u: AddressMode,
+    pub address_mode_v: AddressMode,
+    pub address_mode_w: AddressMode,
+    pub mag_filter: FilterMode,
+    pub min_filter: FilterMode,
+    pub mipmap_filter: FilterMode,
+    pub lod_min_clamp: f32,
+    pub lod_max_clamp: f32,
+    pub compare: Option<CompareFunction>,
+    pub anisotropy_clamp: u16,
+    pub border_color: Option<ImageSamplerBorderColor>,
+}
+
+impl Default for TextureSampler<'_> {
+    fn default() -> Self {
+        Self {
+            label: None,
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Nearest,
+            min_filter: FilterMode::Nearest,
+            mipmap_filter: FilterMode::Nearest,
+            lod_min_clamp: 0.0,
+            lod_max_clamp: 32.0,
+            compare: None,
+            anisotropy_clamp: 1,
+            border_color: None,
+        }
+    }
+}
+
+impl<'a> TextureSamplerBuilder<'a> {
+    /// Sets the same address mode on all three axes.
+    pub fn address_mode(self, mode: AddressMode) -> Self {
+        self.address_mode_u(mode).address_mode_v(mode).address_mode_w(mode)
+    }
+
+    /// Sets the same filter for magnification, minification and mipmaps.
+    pub fn filter(self, mode: FilterMode) -> Self {
+        self.mag_filter(mode).min_filter(mode).mipmap_filter(mode)
+    }
+
+    pub fn descriptor(self) -> SamplerDescriptor<'a> {
+        let sampler = self.build().unwrap();
+        SamplerDescriptor {
+            label: sampler.label,
+            address_mode_u: sampler.address_mode_u,
+            address_mode_v: sampler.address_mode_v,
+            address_mode_w: sampler.address_mode_w,
+            mag_filter: sampler.mag_filter,
+            min_filter: sampler.min_filter,
+            mipmap_filter: sampler.mipmap_filter,
+            lod_min_clamp: sampler.lod_min_clamp,
+            lod_max_clamp: sampler.lod_max_clamp,
+            compare: sampler.compare,
+            anisotropy_clamp: sampler.anisotropy_clamp,
+            border_color: sampler.border_color.map(Into::into),
+        }
+    }
+
+    pub fn create(self, device: &RenderDevice) -> Sampler {
+        device.create_sampler(&self.descriptor())
+    }
+}
+
+#[derive(Builder)]
+#[builder(default, pattern = "owned")] 
+pub struct GpuBuffer<'a> {
+    pub label: Option<&'a str>,
+    pub size: BufferAddress,
+    pub usage: BufferUsages,
+    pub mapped_at_creation: bool,
+}
+
+impl Default for GpuBuffer<'_> {
+    fn default() -> Self {
+        Self {
+            label: None,
+            size: 0,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }
+    }
+}
+
+impl<'a> GpuBufferBuilder<'a> {
+    pub fn descriptor(self) -> BufferDescriptor<'a> {
+        let buffer = self.build().unwrap();
+        BufferDescriptor {
+            label: buffer.label,
+            size: buffer.size,
+            usage: buffer.usage,
+            mapped_at_creation: buffer.mapped_at_creation,
+        }
+    }
+
+    pub fn create(self, device: &RenderDevice) -> Buffer {
+        device.create_buffer(&self.descriptor())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageViewError {
+    /// The builder itself failed, e.g. a field validation error.
+    Builder(String),
+    /// The mip range is empty or reaches past the texture's `mip_level_count`.
+    MipRange { base: u32, count: Option<u32>, available: u32 },
+    /// The layer range is empty or reaches past the texture's array layers.
+    LayerRange { base: u32, count: Option<u32>, available: u32 },
+    /// The view dimension can't be created from a texture of this dimension, e.g. `D3` on a `D2` texture.
+    Dimension { view: TextureViewDimension, texture: TextureDimension },
+    /// The view dimension doesn't fit the number of layers it covers, e.g. `D2` over several layers.
+    LayerCount { view: TextureViewDimension, layers: u32 },
+    /// A `Cube` or `CubeArray` view on a texture whose layers aren't square.
+    CubeNotSquare { width: u32, height: u32 },
+    /// The aspect doesn't exist in the format, e.g. `DepthOnly` on a color format.
+    Aspect { aspect: TextureAspect, format: TextureFormat },
+    /// The view format is neither the texture format nor one of its `view_formats`.
+    Format { view: TextureFormat, texture: TextureFormat },
+    /// The view usage isn't a subset of the texture usage.
+    Usage { view: TextureUsages, texture: TextureUsages },
+}
+
+impl std::fmt::Display for ImageViewError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Builder(error) => write!(f, "failed to build image view: {error}"),
+            Self::MipRange { base, count, available } => 
+                write!(f, "mip range {base}+{count:?} is out of bounds for {available} mip levels"),
+            Self::LayerRange { base, count, available } => 
+                write!(f, "array layer range {base}+{count:?} is out of bounds for {available} layers"),
+            Self::Dimension { view, texture } => 
+                write!(f, "{view:?} view can't be created on a {texture:?} texture"),
+            Self::LayerCount { view, layers } => 
+                write!(f, "{view:?} view can't cover {layers} array layers"),
+            Self::CubeNotSquare { width, height } => 
+                write!(f, "cube views need square layers, texture is {width}x{height}"),
+            Self::Aspect { aspect, format } => 
+                write!(f, "{aspect:?} aspect doesn't exist in {format:?}"),
+            Self::Format { view, texture } => 
+                write!(f, "{view:?} view format isn't compatible with {texture:?} texture"),
+            Self::Usage { view, texture } => 
+                write!(f, "view usage {view:?} isn't a subset of texture usage {texture:?}"),
+        }
+    }
+}
+
+impl std::error::Error for ImageViewError {}
+
+/// Checks a view descriptor against the texture it targets, following wgpu's `create_view` rules.
+/// `None` fields resolve the same way wgpu resolves them: to the remaining mips/layers, and to
+/// a dimension derived from the texture.
+pub fn validate_view(view: &TextureViewDescriptor, texture: &TextureDescriptor) -> Result<(), ImageViewError> {
+
+    let mips = texture.mip_level_count;
+    let mip_count = view.mip_level_count.unwrap_or(mips.saturating_sub(view.base_mip_level));
+    if view.base_mip_level >= mips || mip_count == 0 || view.base_mip_level.checked_add(mip_count).is_none_or(|end| end > mips) {
+        return Err(ImageViewError::MipRange { base: view.base_mip_level, count: view.mip_level_count, available: mips });
+    }
+
+    let layers = texture.array_layer_count();
+    let layer_count = view.array_layer_count.unwrap_or(layers.saturating_sub(view.base_array_layer));
+    if view.base_array_layer >= layers || layer_count == 0 || view.base_array_layer.checked_add(layer_count).is_none_or(|end| end > layers) {
+        return Err(ImageViewError::LayerRange { base: view.base_array_layer, count: view.array_layer_count, available: layers });
+    }
+
+    let dimension = view.dimension.unwrap_or(match texture.dimension {
+        TextureDimension::D1 => TextureViewDimension::D1,
+        TextureDimension::D2 if layers == 1 => TextureViewDimension::D2,
+        TextureDimension::D2 => TextureViewDimension::D2Array,
+        TextureDimension::D3 => TextureViewDimension::D3,
+    });
+    match (dimension, texture.dimension) {
+        (TextureViewDimension::D1, TextureDimension::D1) => {}
+        (TextureViewDimension::D2 | TextureViewDimension::D2Array, TextureDimension::D2) => {}
+        (TextureViewDimension::Cube | TextureViewDimension::CubeArray, TextureDimension::D2) => {}
+        (TextureViewDimension::D3, TextureDimension::D3) => {}
+        (view, texture) => return Err(ImageViewError::Dimension { view, texture }),
+    }
+    let layers_fit = match dimension {
+        TextureViewDimension::D1 | TextureViewDimension::D2 | TextureViewDimension::D3 => layer_count == 1,
+        TextureViewDimension::D2Array => true,
+        TextureViewDimension::Cube => layer_count == 6,
+        TextureViewDimension::CubeArray => layer_count.is_multiple_of(6),
+    };
+    if !layers_fit {
+        return Err(ImageViewError::LayerCount { view: dimension, layers: layer_count });
+    }
+    let Extent3d { width, height, .. } = texture.size;
+    if matches!(dimension, TextureViewDimension::Cube | TextureViewDimension::CubeArray) && width != height {
+        return Err(ImageViewError::CubeNotSquare { width, height });
+    }
+
+    let format = texture.format;
+    let aspect_exists = match view.aspect {
+        TextureAspect::All => true,
+        TextureAspect::DepthOnly => format.has_depth_aspect(),
+        TextureAspect::StencilOnly => format.has_stencil_aspect(),
+        TextureAspect::Plane0 => format.planes().is_some_and(|planes| planes > 0),
+        TextureAspect::Plane1 => format.planes().is_some_and(|planes| planes > 1),
+        TextureAspect::Plane2 => format.planes().is_some_and(|planes| planes > 2),
+    };
+    if !aspect_exists {
+        return Err(ImageViewError::Aspect { aspect: view.aspect, format });
+    }
+
+    if let Some(view_format) = view.format {
+        let compatible = view_format == format
+            || texture.view_formats.contains(&view_format)
+            || format.aspect_specific_format(view.aspect) == Some(view_format);
+        if !compatible {
+            return Err(ImageViewError::Format { view: view_format, texture: format });
+        }
+    }
+
+    if let Some(usage) = view.usage && !texture.usage.contains(usage) {
+        return Err(ImageViewError::Usage { view: usage, texture: texture.usage });
+    }
+
+    Ok(())
+}
+
 
 
 
@@ -73,58 +378,653 @@ macro_rules! define_render_pass_struct {
     };
 }
 
+/// Bind group layouts of a pass, one per tuple element in tuple order.
+/// Elements go to the group of their position, unless they're wrapped in `Group<G, _>` to pick one explicitly.
 pub trait Binds {
     type Layout: Clone + Into<Vec<BindGroupLayout>>;
     
-    fn into_layout(device: &RenderDevice) -> Self::Layout;
+    fn into_layout(world: &World) -> Self::Layout;
+
+    /// Group index of each element, in tuple order.
+    fn groups() -> Vec<u32>;
+
+    /// Layout entries of each element along with its group index, in tuple order.
+    fn layout_entries(device: &RenderDevice) -> Vec<(u32, Vec<BindGroupLayoutEntry>)>;
+}
+
+/// Bind groups of a `Binds` tuple built from its values, indexed by group.
+/// `Param` combines every element's `Bind::Param`, nodes fetch it safely through a cached `SystemState`.
+pub trait AsBindGroups: Binds {
+    type Param: ReadOnlySystemParam + 'static;
+
+    /// Reuses bind groups from `cache` while the underlying GPU resources stay the same, when one is given.
+    /// Gaps between groups are filled with `empty`.
+    fn as_bind_groups(
+        &self, 
+        layouts: &Self::Layout, 
+        device: &RenderDevice, 
+        empty: &EmptyBindGroup,
+        params: &mut SystemParamItem<'_, '_, Self::Param>,
+        cache: Option<(&BindGroupCache, TypeId)>,
+    ) -> Result<Vec<BoundGroup>, AsBindGroupError>;
+}
+
+/// A bind group along with the dynamic offsets it's set with.
+#[derive(Clone)]
+pub struct BoundGroup {
+    pub bind_group: BindGroup,
+    pub offsets: Vec<u32>,
+}
+
+impl From<BindGroup> for BoundGroup {
+    fn from(bind_group: BindGroup) -> Self {
+        Self { bind_group, offsets: vec![] }
+    }
+}
+
+/// A single element of a `Binds` tuple, either an `AsBindGroup` type or a group provided by bevy like `ViewUniformBinds`.
+pub trait Bind {
+    type Param: ReadOnlySystemParam + 'static;
+    /// Explicit group index, `None` takes the element's position in the tuple.
+    const GROUP: Option<u32> = None;
+
+    fn layout(world: &World) -> BindGroupLayout;
+
+    /// Entries `layout` is created from, used to check shaders against it without building a pipeline.
+    fn layout_entries(device: &RenderDevice) -> Vec<BindGroupLayoutEntry>;
+
+    fn bind(
+        &self,
+        layout: &BindGroupLayout,
+        device: &RenderDevice,
+        params: &mut SystemParamItem<'_, '_, Self::Param>,
+        cache: Option<(&BindGroupCache, TypeId, u32)>,
+    ) -> Result<BoundGroup, AsBindGroupError>;
+}
+
+impl<T: AsBindGroup<Param: ReadOnlySystemParam>> Bind for T {
+    type Param = <T as AsBindGroup>::Param;
+
+    fn layout(world: &World) -> BindGroupLayout {
+        T::bind_group_layout(world.resource::<RenderDevice>())
+    }
+
+    fn layout_entries(device: &RenderDevice) -> Vec<BindGroupLayoutEntry> {
+        T::bind_group_layout_entries(device, false)
+    }
+
+    fn bind(
+        &self,
+        layout: &BindGroupLayout,
+        device: &RenderDevice,
+        params: &mut SystemParamItem<'_, '_, Self::Param>,
+        cache: Option<(&BindGroupCache, TypeId, u32)>,
+    ) -> Result<BoundGroup, AsBindGroupError> {
+        let Some((cache, pass, group)) = cache else {
+            return Ok(self.as_bind_group(layout, device, params)?.bind_group.into());
+        };
+        let bindings = self.unprepared_bind_group(layout, device, params, false)?.bindings;
+        Ok(cache.get_or_create(pass, group, T::label(), layout, device, bindings).into())
+    }
+}
+
+/// Binds T at group G rather than at its position in the `Binds` tuple.
+/// Groups left unused in between are filled with empty bind groups.
+#[derive(Deref, DerefMut)]
+pub struct Group<const G: u32, T>(pub T);
+
+impl<const G: u32, T: Bind> Bind for Group<G, T> {
+    type Param = T::Param;
+    const GROUP: Option<u32> = Some(G);
+
+    fn layout(world: &World) -> BindGroupLayout {
+        T::layout(world)
+    }
+
+    fn layout_entries(device: &RenderDevice) -> Vec<BindGroupLayoutEntry> {
+        T::layout_entries(device)
+    }
+
+    fn bind(
+        &self,
+        layout: &BindGroupLayout,
+        device: &RenderDevice,
+        params: &mut SystemParamItem<'_, '_, Self::Param>,
+        cache: Option<(&BindGroupCache, TypeId, u32)>,
+    ) -> Result<BoundGroup, AsBindGroupError> {
+        self.0.bind(layout, device, params, cache)
+    }
+}
+
+/// Bevy's view uniform, bound as `@binding(0) var<uniform> view: View` from `bevy_render::view`.
+/// Build it from the view's `ViewUniformOffset`, it's usually put first to get group 0 like bevy's own passes.
+/// Only the view uniform is bound, bevy's mesh view bindings (lights, shadow maps, environment maps etc.
+/// from `bevy_pbr::mesh_view_bindings`) aren't supported as a `Bind`.
+pub struct ViewUniformBinds {
+    pub offset: u32,
+}
+
+impl From<&ViewUniformOffset> for ViewUniformBinds {
+    fn from(offset: &ViewUniformOffset) -> Self {
+        Self { offset: offset.offset }
+    }
+}
+
+impl Bind for ViewUniformBinds {
+    type Param = SRes<ViewUniforms>;
+
+    fn layout(world: &World) -> BindGroupLayout {
+        let device = world.resource::<RenderDevice>();
+        device.create_bind_group_layout("view_uniform_layout", &Self::layout_entries(device))
+    }
+
+    fn layout_entries(_: &RenderDevice) -> Vec<BindGroupLayoutEntry> {
+        BindGroupLayoutEntries::single(ShaderStages::all(), uniform_buffer::<ViewUniform>(true)).to_vec()
+    }
+
+    fn bind(
+        &self,
+        layout: &BindGroupLayout,
+        device: &RenderDevice,
+        view_uniforms: &mut SystemParamItem<'_, '_, Self::Param>,
+        cache: Option<(&BindGroupCache, TypeId, u32)>,
+    ) -> Result<BoundGroup, AsBindGroupError> {
+        let (Some(buffer), Some(binding)) = (view_uniforms.uniforms.buffer(), view_uniforms.uniforms.binding()) else {
+            return Err(AsBindGroupError::RetryNextUpdate);
+        };
+        let create = || device.create_bind_group("view_uniform", layout, &BindGroupEntries::single(binding));
+        // the buffer only changes when it grows, so the bind group is usually reused across frames
+        let bind_group = match cache {
+            Some((cache, pass, group)) => cache.get_or_insert_with(pass, group, layout, vec![(0, ResourceId::Buffer(buffer.id()))], create),
+            None => create(),
+        };
+        Ok(BoundGroup { bind_group, offsets: vec![self.offset] })
+    }
+}
+
+/// Pipeline layouts for `Binds`, placed at their group indices with `empty`'s layout filling any gaps.
+pub fn pipeline_layouts<B: Binds>(empty: &EmptyBindGroup, layouts: &B::Layout) -> Vec<BindGroupLayout> {
+    let layouts: Vec<_> = layouts.clone().into();
+    place_at_groups(type_name::<B>(), B::groups(), layouts).into_iter()
+        .map(|layout| layout.unwrap_or_else(|| empty.layout.clone()))
+        .collect()
+}
+
+pub(crate) fn group_count(groups: &[u32]) -> usize {
+    groups.iter().max().map_or(0, |max| *max as usize + 1)
+}
+
+/// Puts each item at its group index, leaving `None` in the gaps. Panics if `binds` uses a group twice.
+fn place_at_groups<T>(binds: &str, groups: Vec<u32>, items: Vec<T>) -> Vec<Option<T>> {
+    let mut dense: Vec<_> = std::iter::repeat_with(|| None).take(group_count(&groups)).collect();
+    for (group, item) in groups.into_iter().zip(items) {
+        let slot = &mut dense[group as usize];
+        assert!(slot.is_none(), "{binds} binds group {group} more than once");
+        *slot = Some(item);
+    }
+    dense
+}
+
+/// Orders bind groups by group index, with `empty` filling any gaps.
+fn dense_bind_groups<B: Binds>(bound: Vec<BoundGroup>, empty: &EmptyBindGroup) -> Vec<BoundGroup> {
+    place_at_groups(type_name::<B>(), B::groups(), bound).into_iter()
+        .map(|bound| bound.unwrap_or_else(|| empty.bind_group.clone().into()))
+        .collect()
+}
+
+/// Layout and bind group without any entries, for groups a pass leaves unused between the ones it binds.
+/// Created once along with the first pipeline, then shared by every pass.
+#[derive(Resource, Clone)]
+pub struct EmptyBindGroup {
+    pub layout: BindGroupLayout,
+    pub bind_group: BindGroup,
+}
+
+impl FromWorld for EmptyBindGroup {
+    fn from_world(world: &mut World) -> Self {
+        let device = world.resource::<RenderDevice>();
+        let layout = device.create_bind_group_layout("empty_group_layout", &[]);
+        let bind_group = device.create_bind_group("empty_group", &layout, &[]);
+        Self { layout, bind_group }
+    }
+}
+
+/// Render-world cache of bind groups, keyed on the pass, group index, layout and the identities of the bound resources.
+/// A resized attachment or re-uploaded asset gets new GPU resources and so a new key, stale entries are evicted
+/// after a frame without use. Uniforms are written into fresh buffers each frame and never hit, use push constants for those.
+#[derive(Resource, Default)]
+pub struct BindGroupCache {
+    entries: Mutex<HashMap<BindGroupKey, CachedBindGroup>>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct BindGroupKey {
+    pass: TypeId,
+    group: u32,
+    layout: BindGroupLayoutId,
+    resources: Vec<(u32, ResourceId)>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum ResourceId {
+    Buffer(BufferId),
+    TextureView(TextureViewId),
+    Sampler(SamplerId),
+    Data(Vec<u8>),
+}
+
+impl From<&OwnedBindingResource> for ResourceId {
+    fn from(resource: &OwnedBindingResource) -> Self {
+        match resource {
+            OwnedBindingResource::Buffer(buffer) => Self::Buffer(buffer.id()),
+            OwnedBindingResource::TextureView(_, view) => Self::TextureView(view.id()),
+            OwnedBindingResource::Sampler(_, sampler) => Self::Sampler(sampler.id()),
+            OwnedBindingResource::Data(data) => Self::Data(data.0.clone()),
+        }
+    }
+}
+
+struct CachedBindGroup {
+    bind_group: BindGroup,
+    used: bool,
+}
+
+impl BindGroupCache {
+    pub fn get_or_create(
+        &self,
+        pass: TypeId,
+        group: u32,
+        label: Option<&str>,
+        layout: &BindGroupLayout,
+        device: &RenderDevice,
+        bindings: BindingResources,
+    ) -> BindGroup {
+        let resources = bindings.0.iter().map(|(binding, resource)| (*binding, resource.into())).collect();
+        self.get_or_insert_with(pass, group, layout, resources, || {
+            let bind_group_entries = bindings.0.iter()
+                .map(|(binding, resource)| BindGroupEntry { binding: *binding, resource: resource.get_binding() })
+                .collect::<Vec<_>>();
+            device.create_bind_group(label, layout, &bind_group_entries)
+        })
+    }
+
+    /// For bindings that aren't `OwnedBindingResource`s, e.g. dynamic offset buffers bound with a fixed size.
+    fn get_or_insert_with(
+        &self,
+        pass: TypeId,
+        group: u32,
+        layout: &BindGroupLayout,
+        resources: Vec<(u32, ResourceId)>,
+        create: impl FnOnce() -> BindGroup,
+    ) -> BindGroup {
+        let key = BindGroupKey { pass, group, layout: layout.id(), resources };
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries.get_mut(&key) {
+            entry.used = true;
+            return entry.bind_group.clone();
+        }
+        let bind_group = create();
+        entries.insert(key, CachedBindGroup { bind_group: bind_group.clone(), used: true });
+        bind_group
+    }
+
+    /// Drops every entry that wasn't used since the last call, run once per frame.
+    pub fn evict_unused(&mut self) {
+        let entries = self.entries.get_mut().unwrap();
+        entries.retain(|_, entry| entry.used);
+        entries.values_mut().for_each(|entry| entry.used = false);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 macro_rules! impl_binds {
     () => {
         impl Binds for () {
-            type Layout = [BindGroupLayout; 0];
-            fn into_layout(_: &RenderDevice) -> Self::Layout { [] }
+            type Layout = Vec<BindGroupLayout>;
+            fn into_layout(_: &World) -> Self::Layout { vec![] }
+            fn groups() -> Vec<u32> { vec![] }
+            fn layout_entries(_: &RenderDevice) -> Vec<(u32, Vec<BindGroupLayoutEntry>)> { vec![] }
+        }
+        impl AsBindGroups for () {
+            type Param = ();
+            fn as_bind_groups(
+                &self, 
+                _: &Self::Layout, 
+                _: &RenderDevice, 
+                _: &EmptyBindGroup,
+                _: &mut SystemParamItem<'_, '_, Self::Param>,
+                _: Option<(&BindGroupCache, TypeId)>,
+            ) -> Result<Vec<BoundGroup>, AsBindGroupError> { 
+                Ok(vec![]) 
+            }
         }
     };
-    ($len:expr; $($T:ident => $idx:tt),+ $(,)?) => {
-        impl< $( $T: AsBindGroup ),+ > Binds for ( $( $T ),+, ) {
-            type Layout = [BindGroupLayout; $len];
-            fn into_layout(device: &RenderDevice) -> Self::Layout {
-                [$(<$T as AsBindGroup>::bind_group_layout(device)),+]
+    ($($T:ident => $idx:tt),+) => {
+        impl< $( $T: Bind ),+ > Binds for ( $( $T ),+, ) {
+            type Layout = Vec<BindGroupLayout>;
+            fn into_layout(world: &World) -> Self::Layout {
+                vec![$(<$T as Bind>::layout(world)),+]
+            }
+            fn groups() -> Vec<u32> {
+                vec![$(<$T as Bind>::GROUP.unwrap_or($idx)),+]
+            }
+            fn layout_entries(device: &RenderDevice) -> Vec<(u32, Vec<BindGroupLayoutEntry>)> {
+                Self::groups().into_iter().zip([$(<$T as Bind>::layout_entries(device)),+]).collect()
+            }
+        }
+        impl< $( $T: Bind ),+ > AsBindGroups for ( $( $T ),+, ) {
+            type Param = ( $( $T::Param ),+, );
+            fn as_bind_groups(
+                &self, 
+                layouts: &Self::Layout, 
+                device: &RenderDevice, 
+                empty: &EmptyBindGroup,
+                params: &mut SystemParamItem<'_, '_, Self::Param>,
+                cache: Option<(&BindGroupCache, TypeId)>,
+            ) -> Result<Vec<BoundGroup>, AsBindGroupError> {
+                let groups = Self::groups();
+                let bound = vec![$({
+                    let cache = cache.map(|(cache, pass)| (cache, pass, groups[$idx]));
+                    self.$idx.bind(&layouts[$idx], device, &mut params.$idx, cache)?
+                }),+];
+                Ok(dense_bind_groups::<Self>(bound, empty))
             }
         }
     };
 }
 
+/// Implements `Binds` for every prefix of the given list, i.e. for tuples of 1 up to its length.
+macro_rules! impl_binds_up_to {
+    ([$($done:ident => $done_idx:tt),*]) => {};
+    ([$($done:ident => $done_idx:tt),*] $T:ident => $idx:tt $(, $rest:ident => $rest_idx:tt)*) => {
+        impl_binds!($($done => $done_idx,)* $T => $idx);
+        impl_binds_up_to!([$($done => $done_idx,)* $T => $idx] $($rest => $rest_idx),*);
+    };
+}
+
 impl_binds!();
-impl_binds!(1; A => 0);
-impl_binds!(2; A => 0, B => 1);
-impl_binds!(3; A => 0, B => 1, C => 2);
-impl_binds!(4; A => 0, B => 1, C => 2, D => 3);
-impl_binds!(5; A => 0, B => 1, C => 2, D => 3, E => 4);
-impl_binds!(6; A => 0, B => 1, C => 2, D => 3, E => 4, F => 5);
-impl_binds!(7; A => 0, B => 1, C => 2, D => 3, E => 4, F => 5, G => 6);
-impl_binds!(8; A => 0, B => 1, C => 2, D => 3, E => 4, F => 5, G => 6, H => 7);
+impl_binds_up_to!([] 
+    A => 0, B => 1, C => 2, D => 3, E => 4, F => 5, G => 6, H => 7, 
+    I => 8, J => 9, K => 10, L => 11, M => 12, N => 13, O => 14, P => 15
+);
 
 pub trait Pass {
     type Binds: Binds;
+    /// Values pushed per draw or dispatch, `()` for passes without any. The pipeline gets their range from it.
+    type Constants: MaybePushConstants;
 
     fn shader_defs() -> Vec<ShaderDefVal> { vec![] }
+
+    /// Layouts of groups the pass binds itself rather than through `Binds`, e.g. `UniformRing::layout`.
+    /// They're placed right after the `Binds` groups, in order. Called when the pipeline is created in `Plugin::finish`,
+    /// so resources it reads must be initialized before that, e.g. add `UniformRingPlugin` before the pass's `PassGraph`.
+    fn extra_layouts(_: &World) -> Vec<BindGroupLayout> { vec![] }
+
+    /// Entries of the `extra_layouts`, in the same order, so `BindingValidator` checks those groups too.
+    /// Passes returning any `extra_layouts` should return their entries here, e.g. `UniformRing::layout_entries`.
+    fn extra_layout_entries() -> Vec<Vec<BindGroupLayoutEntry>> { vec![] }
+}
+
+/// Small per-draw or per-dispatch values pushed straight into the command stream, instead of
+/// creating a uniform buffer and bind group for them every frame. Use them as a pass's `Pass::Constants`.
+/// 
+/// The shader declares them behind the injected defs, since devices without `WgpuFeatures::PUSH_CONSTANTS`
+/// get them through a uniform bound at the group right after `Pass::Binds` instead:
+/// ```wgsl
+/// #ifdef PUSH_CONSTANTS
+/// var<push_constant> constants: Constants;
+/// #else
+/// @group(#{PUSH_CONSTANT_GROUP}) @binding(0)
+/// var<uniform> constants: Constants;
+/// #endif
+/// ```
+pub trait PushConstants: ShaderType + WriteInto + Send + Sync + 'static {
+    const STAGES: ShaderStages;
+}
+
+/// A `Pass::Constants`, either `()` or a `PushConstants` type.
+pub trait MaybePushConstants {
+    fn push_constant_range() -> Option<PushConstantRange>;
+}
+
+impl MaybePushConstants for () {
+    fn push_constant_range() -> Option<PushConstantRange> { None }
+}
+
+impl<T: PushConstants> MaybePushConstants for T {
+    /// Sized with uniform layout rules, so the struct matches its WGSL declaration in both cases.
+    fn push_constant_range() -> Option<PushConstantRange> {
+        let size = T::min_size().get() as u32;
+        Some(PushConstantRange { stages: T::STAGES, range: 0..size })
+    }
+}
+
+/// A pipeline's push constant range, or the uniform standing in for it on devices without push constants.
+#[derive(Clone)]
+pub struct PushConstantSlot {
+    range: PushConstantRange,
+    fallback: Option<Arc<PushConstantFallback>>,
+}
+
+impl PushConstantSlot {
+    pub(crate) fn new<P: Pass>(device: &RenderDevice, group: usize) -> Option<Self> {
+        let range = P::Constants::push_constant_range()?;
+        let fallback = (!supports_push_constants(device.features(), &device.limits(), &range)).then(|| {
+            let entries = fallback_layout_entries(&range);
+            let layout = device.create_bind_group_layout("push_constant_fallback_layout", &entries);
+            Arc::new(PushConstantFallback { group: group as u32, layout, ring: default() })
+        });
+        Some(Self { range, fallback })
+    }
+
+    pub fn is_fallback(&self) -> bool {
+        self.fallback.is_some()
+    }
+
+    fn push_constant_ranges(&self) -> Vec<PushConstantRange> {
+        match self.fallback {
+            Some(_) => vec![],
+            None => vec![self.range.clone()],
+        }
+    }
+
+    fn layout(&self) -> Option<BindGroupLayout> {
+        self.fallback.as_ref().map(|fallback| fallback.layout.clone())
+    }
+
+    /// Group index and entries of the uniform fallback.
+    pub(crate) fn layout_entries(&self) -> Option<(u32, Vec<BindGroupLayoutEntry>)> {
+        self.fallback.as_ref().map(|fallback| (fallback.group, fallback_layout_entries(&self.range)))
+    }
+
+    pub(crate) fn shader_defs(&self) -> Vec<ShaderDefVal> {
+        match &self.fallback {
+            Some(fallback) => vec![ShaderDefVal::UInt("PUSH_CONSTANT_GROUP".into(), fallback.group)],
+            None => vec![ShaderDefVal::Bool("PUSH_CONSTANTS".into(), true)],
+        }
+    }
+
+    pub fn set_render<T: ShaderType + WriteInto>(&self, render_pass: &mut TrackedRenderPass, world: &World, value: &T) {
+        let bytes = encode_push_constants(value);
+        match &self.fallback {
+            Some(fallback) => {
+                let (bind_group, offset) = fallback.write(world, &bytes);
+                render_pass.wgpu_pass().set_bind_group(fallback.group, &*bind_group, &[offset]);
+            }
+            None => render_pass.set_push_constants(self.range.stages, 0, &bytes),
+        }
+    }
+
+    pub fn set_compute<T: ShaderType + WriteInto>(&self, compute_pass: &mut ComputePass, world: &World, value: &T) {
+        let bytes = encode_push_constants(value);
+        match &self.fallback {
+            Some(fallback) => {
+                let (bind_group, offset) = fallback.write(world, &bytes);
+                compute_pass.set_bind_group(fallback.group, &*bind_group, &[offset]);
+            }
+            None => compute_pass.set_push_constants(0, &bytes),
+        }
+    }
+}
+
+/// Uniform bound in place of push constants. Each value set during a frame gets its own slice of one buffer,
+/// selected by dynamic offset, rather than a buffer and bind group of its own.
+struct PushConstantFallback {
+    group: u32,
+    layout: BindGroupLayout,
+    ring: Mutex<FallbackRing>,
+}
+
+#[derive(Default)]
+struct FallbackRing {
+    /// The buffer, its bind group and how many values fit in it.
+    buffer: Option<(Buffer, BindGroup, u64)>,
+    used: u64,
+    frame: u32,
+}
+
+impl PushConstantFallback {
+    /// Writes `bytes` to the next free slice of the frame, returning the bind group and the slice's offset.
+    fn write(&self, world: &World, bytes: &[u8]) -> (BindGroup, u32) {
+        let device = world.resource::<RenderDevice>();
+        let frame = world.resource::<FrameCount>().0;
+        let stride = fallback_stride(bytes.len(), &device.limits());
+
+        let mut ring = self.ring.lock().unwrap();
+        let ring = &mut *ring;
+        if ring.frame != frame {
+            ring.frame = frame;
+            ring.used = 0;
+        }
+        if ring.buffer.as_ref().is_none_or(|(_, _, capacity)| ring.used == *capacity) {
+            // values already set this frame keep the previous buffer alive through its bind group
+            let capacity = ring.buffer.as_ref().map_or(16, |(_, _, capacity)| capacity * 2);
+            let buffer = device.create_buffer(&BufferDescriptor {
+                label: Some("push_constant_fallback_buffer"),
+                size: capacity * stride,
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let binding = BufferBinding { buffer: &buffer, offset: 0, size: NonZeroU64::new(bytes.len() as u64) };
+            let bind_group = device.create_bind_group("push_constant_fallback", &self.layout, &BindGroupEntries::single(binding));
+            ring.buffer = Some((buffer, bind_group, capacity));
+            ring.used = 0;
+        }
+        let (buffer, bind_group, _) = ring.buffer.as_ref().unwrap();
+        let offset = ring.used * stride;
+        world.resource::<RenderQueue>().write_buffer(buffer, offset, bytes);
+        ring.used += 1;
+        (bind_group.clone(), offset as u32)
+    }
+}
+
+/// Whether the device can push `range` directly, otherwise the slot falls back to a uniform.
+fn supports_push_constants(features: WgpuFeatures, limits: &WgpuLimits, range: &PushConstantRange) -> bool {
+    features.contains(WgpuFeatures::PUSH_CONSTANTS) && limits.max_push_constant_size >= range.range.end
+}
+
+/// Distance between consecutive values in the fallback buffer, so each dynamic offset is aligned.
+fn fallback_stride(len: usize, limits: &WgpuLimits) -> u64 {
+    (len as u64).next_multiple_of(limits.min_uniform_buffer_offset_alignment as u64)
+}
+
+fn fallback_layout_entries(range: &PushConstantRange) -> Vec<BindGroupLayoutEntry> {
+    let size = NonZeroU64::new(range.range.end as u64);
+    BindGroupLayoutEntries::single(range.stages, uniform_buffer_sized(true, size)).to_vec()
+}
+
+fn encode_push_constants<T: ShaderType + WriteInto>(value: &T) -> Vec<u8> {
+    let mut buffer = encase::UniformBuffer::new(Vec::new());
+    buffer.write(value).unwrap();
+    buffer.into_inner()
+}
+
+/// Type name of pass P without its module path, e.g. `DrawCanvasPass`.
+/// Names the debug groups and render diagnostics of the pass's node.
+pub fn pass_name<P: ?Sized>() -> &'static str {
+    let name = type_name::<P>();
+    let path_end = name.find('<').unwrap_or(name.len());
+    name[..path_end].rfind("::").map_or(name, |index| &name[index + 2..])
+}
+
+/// Asset paths loaded in place of a pass's shader, keyed by the path the pass declares.
+/// Lets apps swap a shader embedded in a library (`embedded://...`) for their own file, see `ShaderOverrideExt`.
+#[derive(Resource, Default, Clone)]
+pub struct ShaderOverrides(HashMap<String, String>);
+
+impl ShaderOverrides {
+    pub fn insert(&mut self, default: impl Into<String>, path: impl Into<String>) {
+        self.0.insert(default.into(), path.into());
+    }
+
+    /// The override of `path`, or `path` itself.
+    pub fn resolve<'a>(&'a self, path: &'a str) -> &'a str {
+        self.0.get(path).map_or(path, String::as_str)
+    }
+}
+
+pub trait ShaderOverrideExt {
+    /// Loads `path` wherever a pass would load `default`, in both the main and render world.
+    /// Call it before the pipelines are created, i.e. before `App::finish`.
+    fn override_shader(&mut self, default: &str, path: &str) -> &mut Self;
+}
+
+impl ShaderOverrideExt for App {
+    fn override_shader(&mut self, default: &str, path: &str) -> &mut Self {
+        self.world_mut().get_resource_or_init::<ShaderOverrides>().insert(default, path);
+        if let Some(render_app) = self.get_sub_app_mut(RenderApp) {
+            render_app.world_mut().get_resource_or_init::<ShaderOverrides>().insert(default, path);
+        }
+        self
+    }
+}
+
+/// Loads the shader at `path`, or at its override in `ShaderOverrides`.
+pub fn load_shader(world: &World, path: &str) -> Handle<Shader> {
+    let path = world.get_resource::<ShaderOverrides>().map_or(path, |overrides| overrides.resolve(path));
+    world.load_asset(path)
 }
 
 pub trait Compute {
     const COMPUTE_SHADER_PATH: &'static str;
+    const ENTRY_POINT: &'static str = "compute";
+    /// Injected into the shader as `WORKGROUP_SIZE_X/Y/Z` defs, use them in `@workgroup_size(..)`
+    /// so the dispatch counts computed on the Rust side always match the shader.
+    const WORKGROUP_SIZE: UVec3 = UVec3::new(8, 8, 1);
+
+    fn workgroup_shader_defs() -> Vec<ShaderDefVal> {
+        vec![
+            ShaderDefVal::UInt("WORKGROUP_SIZE_X".into(), Self::WORKGROUP_SIZE.x),
+            ShaderDefVal::UInt("WORKGROUP_SIZE_Y".into(), Self::WORKGROUP_SIZE.y),
+            ShaderDefVal::UInt("WORKGROUP_SIZE_Z".into(), Self::WORKGROUP_SIZE.z),
+        ]
+    }
+
+    /// Number of workgroups needed to cover `extent` invocations, rounding up.
+    fn workgroups(extent: UVec3) -> UVec3 {
+        let size = Self::WORKGROUP_SIZE;
+        UVec3::new(extent.x.div_ceil(size.x), extent.y.div_ceil(size.y), extent.z.div_ceil(size.z))
+    }
 }
 
 #[derive(Resource, Deref)]
 pub struct PipelineCompute<P: Pass> {
     #[deref]
     layouts: <P::Binds as Binds>::Layout,
+    push_constants: Option<PushConstantSlot>,
     id: CachedComputePipelineId,
 }
 
-impl<P: Pass + Compute> PipelineCompute<P> {
+impl<P: Pass> PipelineCompute<P> {
     pub fn id(&self) -> CachedComputePipelineId { self.id }
+    pub fn push_constants(&self) -> Option<&PushConstantSlot> { self.push_constants.as_ref() }
 }
 
 impl<P: Pass + Compute> FromWorld for PipelineCompute<P> {
@@ -133,42 +1033,99 @@ impl<P: Pass + Compute> FromWorld for PipelineCompute<P> {
         let name = type_name::<P>();
         info!("Creating {name} Compute Pass");
 
+        world.init_resource::<EmptyBindGroup>();
         let device = world.resource::<RenderDevice>();
-        let layouts = P::Binds::into_layout(device);
-        let shader = world.load_asset(P::COMPUTE_SHADER_PATH);
-        let entry_point = "compute".into();
+        let layouts = P::Binds::into_layout(world);
+        let mut layout = pipeline_layouts::<P::Binds>(world.resource::<EmptyBindGroup>(), &layouts);
+        layout.extend(P::extra_layouts(world));
+        let push_constants = PushConstantSlot::new::<P>(device, layout.len());
+        layout.extend(push_constants.iter().flat_map(PushConstantSlot::layout));
+        let shader = load_shader(world, P::COMPUTE_SHADER_PATH);
+        let entry_point = P::ENTRY_POINT.into();
+        let mut shader_defs = P::shader_defs();
+        shader_defs.extend(P::workgroup_shader_defs());
+        shader_defs.extend(push_constants.iter().flat_map(PushConstantSlot::shader_defs));
         let pipeline_cache = world.resource_mut::<PipelineCache>();
         let id = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor { 
             label: Some(name.into()), 
-            layout: layouts.clone().into(),
+            layout,
             shader,
             entry_point,
-            push_constant_ranges: vec![],
-            shader_defs: P::shader_defs(),
+            push_constant_ranges: push_constants.iter().flat_map(PushConstantSlot::push_constant_ranges).collect(),
+            shader_defs,
             zero_initialize_workgroup_memory: true,
         });
-        Self { layouts, id }
+        Self { layouts, push_constants, id }
     }
 }
 
 pub trait Raster {
+    /// Source of both stages, unless `vertex_shader` or `fragment_shader` is overridden.
     const VERTEX_FRAGMENT_SHADER_PATH: &'static str;
-
+    const VERTEX_ENTRY_POINT: &'static str = "vertex";
+    const FRAGMENT_ENTRY_POINT: &'static str = "fragment";
+    /// Vertices drawn by the default `RasterPass::draw`, change it along with `primitive` for other topologies.
+    const VERTEX_COUNT: u32 = 4;
+
+    fn vertex_shader(world: &World) -> Handle<Shader> { load_shader(world, Self::VERTEX_FRAGMENT_SHADER_PATH) }
+    fn fragment_shader(world: &World) -> Handle<Shader> { load_shader(world, Self::VERTEX_FRAGMENT_SHADER_PATH) }
+    fn vertex_state(world: &World, shader_defs: Vec<ShaderDefVal>) -> VertexState {
+        let shader = Self::vertex_shader(world);
+        let entry_point = Self::VERTEX_ENTRY_POINT.into();
+        let buffers = Self::vertex_buffers();
+        VertexState { shader, shader_defs, entry_point, buffers }
+    }
+    /// Defaults to quads drawn as 4 vertex triangle strips with no culling, e.g. billboards with only a front-face.
+    fn primitive() -> PrimitiveState {
+        PrimitiveState { 
+            topology: PrimitiveTopology::TriangleStrip,
+            cull_mode: None,
+            ..default()
+        }
+    }
     fn multisample() -> MultisampleState { default() }
     fn vertex_buffers() -> Vec<VertexBufferLayout> { vec![] }
     fn depth_stencil() -> Option<DepthStencilState> { None }
     fn fragment_targets() -> Vec<Option<ColorTargetState>> { vec![] }
 }
 
+/// `Raster` for post-process style passes, drawn as bevy's fullscreen triangle so only the fragment stage is supplied.
+/// The fragment shader can take `FullscreenVertexOutput` from `bevy_core_pipeline::fullscreen_vertex_shader` as input.
+pub trait FullscreenRaster {
+    const FRAGMENT_SHADER_PATH: &'static str;
+    const FRAGMENT_ENTRY_POINT: &'static str = "fragment";
+
+    fn multisample() -> MultisampleState { default() }
+    fn depth_stencil() -> Option<DepthStencilState> { None }
+    fn fragment_targets() -> Vec<Option<ColorTargetState>> { vec![] }
+}
+
+impl<F: FullscreenRaster> Raster for F {
+    const VERTEX_FRAGMENT_SHADER_PATH: &'static str = F::FRAGMENT_SHADER_PATH;
+    const FRAGMENT_ENTRY_POINT: &'static str = F::FRAGMENT_ENTRY_POINT;
+    const VERTEX_COUNT: u32 = 3;
+
+    fn vertex_shader(_: &World) -> Handle<Shader> { FULLSCREEN_SHADER_HANDLE }
+    fn vertex_state(_: &World, _: Vec<ShaderDefVal>) -> VertexState { fullscreen_shader_vertex_state() }
+    fn multisample() -> MultisampleState { F::multisample() }
+    fn depth_stencil() -> Option<DepthStencilState> { F::depth_stencil() }
+    fn fragment_targets() -> Vec<Option<ColorTargetState>> { F::fragment_targets() }
+}
+
 #[derive(Resource, Deref)]
 pub struct RasterPipeline<P: Pass> {
     #[deref]
     layouts: <P::Binds as Binds>::Layout,
+    push_constants: Option<PushConstantSlot>,
+    /// Unspecialized descriptor, kept around as the base for `SpecializedRaster` variants.
+    descriptor: RenderPipelineDescriptor,
     id: CachedRenderPipelineId,
 }
 
-impl<P: Pass + Raster> RasterPipeline<P> {
+impl<P: Pass> RasterPipeline<P> {
     pub fn id(&self) -> CachedRenderPipelineId { self.id }
+    pub fn push_constants(&self) -> Option<&PushConstantSlot> { self.push_constants.as_ref() }
+    pub fn descriptor(&self) -> &RenderPipelineDescriptor { &self.descriptor }
 }
 
 impl<P: Pass + Raster> FromWorld for RasterPipeline<P> {
@@ -177,75 +1134,144 @@ impl<P: Pass + Raster> FromWorld for RasterPipeline<P> {
         let name = type_name::<P>();
         info!("Creating {name} Raster Pass");
         
-        let vertex = get_vertex::<P>(world);
-        let fragment = Some(get_fragment::<P>(world));
+        world.init_resource::<EmptyBindGroup>();
         let device = world.resource::<RenderDevice>();
-        let layouts = P::Binds::into_layout(device);
-        let id = world.resource_mut::<PipelineCache>()
-            .queue_render_pipeline(RenderPipelineDescriptor {
-                label: Some(name.into()),
-                layout: layouts.clone().into(),
-                vertex,
-                primitive: PrimitiveState { 
-                    topology: PrimitiveTopology::TriangleStrip, // quads are drawn using only 4 vertices
-                    cull_mode: None, // render billboarded quads with only a front-face and no back-face
-                    ..default()
-                },
-                fragment,
-                depth_stencil: P::depth_stencil(),
-                multisample: P::multisample(),
-                push_constant_ranges: vec![],
-                zero_initialize_workgroup_memory: true,
-            }
-        );
-        Self { layouts, id }
+        let layouts = P::Binds::into_layout(world);
+        let mut layout = pipeline_layouts::<P::Binds>(world.resource::<EmptyBindGroup>(), &layouts);
+        layout.extend(P::extra_layouts(world));
+        let push_constants = PushConstantSlot::new::<P>(device, layout.len());
+        layout.extend(push_constants.iter().flat_map(PushConstantSlot::layout));
+        let mut shader_defs = P::shader_defs();
+        shader_defs.extend(push_constants.iter().flat_map(PushConstantSlot::shader_defs));
+        let vertex = P::vertex_state(world, shader_defs.clone());
+        let fragment = Some(get_fragment::<P>(world, shader_defs));
+        let descriptor = RenderPipelineDescriptor {
+            label: Some(name.into()),
+            layout,
+            vertex,
+            primitive: P::primitive(),
+            fragment,
+            depth_stencil: P::depth_stencil(),
+            multisample: P::multisample(),
+            push_constant_ranges: push_constants.iter().flat_map(PushConstantSlot::push_constant_ranges).collect(),
+            zero_initialize_workgroup_memory: true,
+        };
+        let id = world.resource_mut::<PipelineCache>().queue_render_pipeline(descriptor.clone());
+        Self { layouts, push_constants, descriptor, id }
     }
 }
 
-fn get_vertex<P: Pass + Raster>(world: &mut World) -> VertexState {
-    let shader = world.load_asset(P::VERTEX_FRAGMENT_SHADER_PATH);
-    let entry_point = "vertex".into();
-    let shader_defs = P::shader_defs();
-    let buffers = P::vertex_buffers(); 
-    VertexState { shader, shader_defs, entry_point, buffers }
+/// View properties a `SpecializedRaster` pipeline is keyed on.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct RasterKey {
+    /// Format of the view's main texture, `Rgba16Float` on HDR cameras.
+    pub format: TextureFormat,
+    /// MSAA sample count of the view, for passes that draw into the multisampled main pass.
+    pub samples: u32,
+    /// Pass-defined bits, taken from the view's `RasterKeyBits<P>`.
+    pub bits: u64,
+    /// Defs from the view's `ShaderDefSettings` components, added to both stages before `specialize` runs.
+    pub shader_defs: Vec<ShaderDefVal>,
 }
 
-fn get_fragment<P: Pass + Raster>(world: &mut World) -> FragmentState {
-    let shader = world.load_asset(P::VERTEX_FRAGMENT_SHADER_PATH);
-    let entry_point = "fragment".into();
-    let shader_defs = P::shader_defs();
-    let targets = P::fragment_targets();
-    FragmentState { shader, shader_defs, entry_point, targets }
+/// `Raster` pass whose pipeline is specialized per view by `SpecializedRasterPlugin<P>`,
+/// so the same pass works on HDR, MSAA and LDR cameras alike.
+pub trait SpecializedRaster: Pass + Raster + Send + Sync + 'static {
+    /// Adjusts the unspecialized descriptor for a view.
+    /// Defaults to the view's main texture format in every color target and its MSAA sample count,
+    /// passes writing to single-sampled textures like `ViewTarget::post_process_write` only use `set_target_formats`.
+    fn specialize(key: RasterKey, descriptor: &mut RenderPipelineDescriptor) {
+        set_target_formats(key.format, descriptor);
+        descriptor.multisample.count = key.samples;
+    }
 }
 
+/// Writes `format` into every color target of the descriptor.
+pub fn set_target_formats(format: TextureFormat, descriptor: &mut RenderPipelineDescriptor) {
+    if let Some(fragment) = &mut descriptor.fragment {
+        for target in fragment.targets.iter_mut().flatten() {
+            target.format = format;
+        }
+    }
+}
+
+impl<P: SpecializedRaster> SpecializedRenderPipeline for RasterPipeline<P> {
+    type Key = RasterKey;
 
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        let mut descriptor = self.descriptor.clone();
+        descriptor.vertex.shader_defs.extend(key.shader_defs.iter().cloned());
+        if let Some(fragment) = &mut descriptor.fragment {
+            fragment.shader_defs.extend(key.shader_defs.iter().cloned());
+        }
+        P::specialize(key, &mut descriptor);
+        descriptor
+    }
+}
 
+/// Per-camera settings turned into shader defs, e.g. `DrawSettings { debug_overlay: true }` into `DEBUG_OVERLAY`.
+/// Each combination compiles its own pipeline variant, register it for a pass with `ShaderDefSettingsPlugin<P, S>`.
+pub trait ShaderDefSettings: Component {
+    fn shader_defs(&self) -> Vec<ShaderDefVal>;
+}
 
+/// Render-world list of where a pass's per-view shader defs come from, one entry per `ShaderDefSettings` type.
+#[derive(Resource, Deref, DerefMut)]
+pub struct ViewShaderDefSources<P> {
+    #[deref]
+    sources: Vec<fn(EntityRef) -> Vec<ShaderDefVal>>,
+    marker: PhantomData<P>,
+}
+
+impl<P> Default for ViewShaderDefSources<P> {
+    fn default() -> Self {
+        Self { sources: vec![], marker: PhantomData }
+    }
+}
+
+impl<P> ViewShaderDefSources<P> {
+    pub fn push<S: ShaderDefSettings>(&mut self) {
+        self.sources.push(|view| view.get::<S>().map(S::shader_defs).unwrap_or_default());
+    }
 
+    pub fn shader_defs(&self, view: EntityRef) -> Vec<ShaderDefVal> {
+        self.sources.iter().flat_map(|source| source(view)).collect()
+    }
+}
 
+/// User-defined `RasterKey::bits` for pass P, insert it on the camera.
+#[derive(Component, Deref, DerefMut)]
+pub struct RasterKeyBits<P> {
+    #[deref]
+    pub bits: u64,
+    marker: PhantomData<P>,
+}
 
+impl<P> RasterKeyBits<P> {
+    pub fn new(bits: u64) -> Self {
+        Self { bits, marker: PhantomData }
+    }
+}
 
+impl<P> Clone for RasterKeyBits<P> {
+    fn clone(&self) -> Self {
+        Self::new(self.bits)
+    }
+}
 
+impl<P: Send + Sync + 'static> ExtractComponent for RasterKeyBits<P> {
+    type QueryData = &'static Self;
+    type QueryFilter = ();
+    type Out = Self;
 
-pub type BindingGroupParam<'a> = (
-    Res<'a, RenderAssets<GpuImage>>, 
-    Res<'a, FallbackImage>, 
-    Res<'a, RenderAssets<GpuShaderStorageBuffer>>
-);
+    fn extract_component(bits: &Self) -> Option<Self> { Some(bits.clone()) }
+}
 
-/// Gets the params required for AsBindGroup for running a custom render pipeline pass.
-/// Currently we're not able to get Res<R> from the World, which is the required format
-/// so using transmute as a workaround.
-/// 
-/// TRACK the issue: https://github.com/bevyengine/bevy/issues/16831
-pub fn get_binding_group_params(world: &World) -> BindingGroupParam {
-    let gpu_images = world.resource_ref::<RenderAssets<GpuImage>>();
-    let gpu_images: Res<RenderAssets<GpuImage>> = unsafe { std::mem::transmute(gpu_images) };
-    let fallback_image = world.resource_ref::<FallbackImage>();
-    let fallback_image: Res<FallbackImage> = unsafe { std::mem::transmute(fallback_image) };
-    let gpu_ssbos = world.resource_ref::<RenderAssets<GpuShaderStorageBuffer>>();
-    let gpu_ssbos: Res<RenderAssets<GpuShaderStorageBuffer>> = unsafe { std::mem::transmute(gpu_ssbos) };
-    (gpu_images, fallback_image, gpu_ssbos)
+fn get_fragment<P: Pass + Raster>(world: &World, shader_defs: Vec<ShaderDefVal>) -> FragmentState {
+    let shader = P::fragment_shader(world);
+    let entry_point = P::FRAGMENT_ENTRY_POINT.into();
+    let targets = P::fragment_targets();
+    FragmentState { shader, shader_defs, entry_point, targets }
 }
 
 
@@ -257,6 +1283,16 @@ pub fn get_binding_group_params(world: &World) -> BindingGroupParam {
 
 
 
+
+
+
+
+
+
+
+
+
+
 
 
 
@@ -281,3 +1317,575 @@ impl<U: ShaderType + WriteInto> IntoUniform for U {
         Uniform { uniform: self }
     }
 }
+
+/// Render-world ring of `T` values sharing one `DynamicUniformBuffer`, bound once and selected per draw by dynamic offset.
+/// Push values during `RenderSet::Prepare`, they're uploaded in `RenderSet::PrepareBindGroups` and cleared when the next frame is extracted.
+/// Add the ring's group to a pass by returning `UniformRing::layout` from `Pass::extra_layouts`, and its entries from `Pass::extra_layout_entries`.
+#[derive(Resource)]
+pub struct UniformRing<T: ShaderType + WriteInto> {
+    buffer: DynamicUniformBuffer<T>,
+    offsets: Vec<u32>,
+    layout: BindGroupLayout,
+    bind_group: Option<(BufferId, BindGroup)>,
+}
+
+impl<T: ShaderType + WriteInto> FromWorld for UniformRing<T> {
+    fn from_world(world: &mut World) -> Self {
+        let layout = world.resource::<RenderDevice>().create_bind_group_layout("uniform_ring_layout", &Self::layout_entries());
+        Self { buffer: default(), offsets: vec![], layout, bind_group: None }
+    }
+}
+
+impl<T: ShaderType + WriteInto> UniformRing<T> {
+    /// Layout of the ring's group, shared by every pipeline binding it.
+    pub fn layout(&self) -> &BindGroupLayout {
+        &self.layout
+    }
+
+    /// Entries of `layout`, for `Pass::extra_layout_entries`.
+    pub fn layout_entries() -> Vec<BindGroupLayoutEntry> {
+        BindGroupLayoutEntries::single(ShaderStages::all(), uniform_buffer::<T>(true)).to_vec()
+    }
+
+    /// Queues a value for this frame, returning its dynamic offset.
+    pub fn push(&mut self, value: &T) -> u32 {
+        let offset = self.buffer.push(value);
+        self.offsets.push(offset);
+        offset
+    }
+
+    /// Offsets of this frame's values, in push order.
+    pub fn offsets(&self) -> &[u32] {
+        &self.offsets
+    }
+
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// `None` until values have been pushed and uploaded.
+    pub fn bind_group(&self) -> Option<&BindGroup> {
+        self.bind_group.as_ref().map(|(_, bind_group)| bind_group)
+    }
+
+    pub fn set_render(&self, render_pass: &mut TrackedRenderPass, group: u32, offset: u32) {
+        if let Some(bind_group) = self.bind_group() {
+            render_pass.wgpu_pass().set_bind_group(group, &**bind_group, &[offset]);
+        }
+    }
+
+    pub fn set_compute(&self, compute_pass: &mut ComputePass, group: u32, offset: u32) {
+        if let Some(bind_group) = self.bind_group() {
+            compute_pass.set_bind_group(group, &**bind_group, &[offset]);
+        }
+    }
+
+    /// Uploads the pushed values, only recreating the bind group when the buffer had to grow.
+    pub fn write(&mut self, device: &RenderDevice, queue: &RenderQueue) {
+        if self.offsets.is_empty() {
+            return;
+        }
+        self.buffer.write_buffer(device, queue);
+        let Some(buffer) = self.buffer.buffer() else {
+            return;
+        };
+        if self.bind_group.as_ref().is_some_and(|(id, _)| *id == buffer.id()) {
+            return;
+        }
+        let binding = self.buffer.binding().unwrap();
+        let bind_group = device.create_bind_group("uniform_ring", &self.layout, &BindGroupEntries::single(binding));
+        self.bind_group = Some((buffer.id(), bind_group));
+    }
+
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.offsets.clear();
+    }
+}
+
+/// Adds a `UniformRing<T>` to the render world, clearing it at the start of each frame and uploading it once prepared.
+pub struct UniformRingPlugin<T>(PhantomData<T>);
+
+impl<T> Default for UniformRingPlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: ShaderType + WriteInto + Send + Sync + 'static> Plugin for UniformRingPlugin<T> {
+    fn build(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            // cleared while the frame is extracted, so the ring only ever holds the values pushed for the current one
+            render_app.add_systems(ExtractSchedule, clear_uniform_ring::<T>);
+            render_app.add_systems(Render, write_uniform_ring::<T>.in_set(RenderSet::PrepareBindGroups));
+        }
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<UniformRing<T>>();
+        }
+    }
+}
+
+fn write_uniform_ring<T: ShaderType + WriteInto + Send + Sync + 'static>(
+    mut ring: ResMut<UniformRing<T>>,
+    device: Res<RenderDevice>,
+    queue: Res<RenderQueue>,
+) {
+    ring.write(&device, &queue);
+}
+
+fn clear_uniform_ring<T: ShaderType + WriteInto + Send + Sync + 'static>(mut ring: ResMut<UniformRing<T>>) {
+    ring.clear();
+}
+
+// storage utils
+
+/// Access mode of a storage binding, `ReadOnly` or `ReadWrite`.
+pub trait StorageAccess: Send + Sync + 'static {
+    const READ_ONLY: bool;
+    /// Writable storage isn't allowed in vertex shaders without an extra device feature.
+    const VISIBILITY: ShaderStages;
+
+    fn layout_entry(min_binding_size: Option<NonZeroU64>) -> BindGroupLayoutEntry {
+        let entry = if Self::READ_ONLY {
+            storage_buffer_read_only_sized(false, min_binding_size)
+        } else {
+            storage_buffer_sized(false, min_binding_size)
+        };
+        entry.build(0, Self::VISIBILITY)
+    }
+}
+
+pub struct ReadOnly;
+pub struct ReadWrite;
+
+impl StorageAccess for ReadOnly {
+    const READ_ONLY: bool = true;
+    const VISIBILITY: ShaderStages = ShaderStages::all();
+}
+
+impl StorageAccess for ReadWrite {
+    const READ_ONLY: bool = false;
+    const VISIBILITY: ShaderStages = ShaderStages::FRAGMENT.union(ShaderStages::COMPUTE);
+}
+
+/// Read-only storage buffer counterpart to `Uniform`, bound at binding 0 and uploaded into a new buffer on each `as_bind_group`.
+/// Since the buffer doesn't outlive the bind group, shader writes would be lost,
+/// bind a `StorageAsset<ReadWrite>` for results that should persist instead.
+#[derive(Deref, DerefMut)]
+pub struct Storage<S: ShaderType + WriteInto> {
+    pub storage: S,
+}
+
+/// Runtime-sized array of `T`, `array<T>` on the shader side.
+pub type StorageVec<T> = Storage<Vec<T>>;
+
+impl<S: ShaderType + WriteInto> Storage<S> {
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+}
+
+impl<S: ShaderType + WriteInto + Send + Sync> AsBindGroup for Storage<S> {
+    type Data = ();
+    type Param = ();
+
+    fn label() -> Option<&'static str> {
+        Some("storage")
+    }
+
+    fn unprepared_bind_group(
+        &self,
+        _: &BindGroupLayout,
+        device: &RenderDevice,
+        _: &mut SystemParamItem<'_, '_, Self::Param>,
+        _: bool,
+    ) -> Result<UnpreparedBindGroup<Self::Data>, AsBindGroupError> {
+        let mut contents = encase::StorageBuffer::new(Vec::new());
+        contents.write(&self.storage).unwrap();
+        let buffer = device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Self::label(),
+            contents: &contents.into_inner(),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+        });
+        let bindings = BindingResources(vec![(0, OwnedBindingResource::Buffer(buffer))]);
+        Ok(UnpreparedBindGroup { bindings, data: () })
+    }
+
+    fn bind_group_layout_entries(_: &RenderDevice, _: bool) -> Vec<BindGroupLayoutEntry> {
+        vec![ReadOnly::layout_entry(Some(S::min_size()))]
+    }
+}
+
+pub trait IntoStorage: Sized + ShaderType + WriteInto {
+    fn into_storage(self) -> Storage<Self>;
+}
+
+impl<S: ShaderType + WriteInto> IntoStorage for S {
+    fn into_storage(self) -> Storage<S> {
+        Storage::new(self)
+    }
+}
+
+/// Binds a `ShaderStorageBuffer` asset at binding 0, reusing its uploaded `GpuShaderStorageBuffer` rather than copying.
+/// Suited to data that persists across frames, e.g. particles or histograms written by a compute pass.
+pub struct StorageAsset<A: StorageAccess = ReadOnly> {
+    pub handle: Handle<ShaderStorageBuffer>,
+    marker: PhantomData<A>,
+}
+
+impl<A: StorageAccess> StorageAsset<A> {
+    pub fn new(handle: Handle<ShaderStorageBuffer>) -> Self {
+        Self { handle, marker: PhantomData }
+    }
+}
+
+impl<A: StorageAccess> Clone for StorageAsset<A> {
+    fn clone(&self) -> Self {
+        Self::new(self.handle.clone())
+    }
+}
+
+impl<A: StorageAccess> AsBindGroup for StorageAsset<A> {
+    type Data = ();
+    type Param = SRes<RenderAssets<GpuShaderStorageBuffer>>;
+
+    fn label() -> Option<&'static str> {
+        Some("storage_asset")
+    }
+
+    fn unprepared_bind_group(
+        &self,
+        _: &BindGroupLayout,
+        _: &RenderDevice,
+        gpu_ssbos: &mut SystemParamItem<'_, '_, Self::Param>,
+        _: bool,
+    ) -> Result<UnpreparedBindGroup<Self::Data>, AsBindGroupError> {
+        let Some(gpu_ssbo) = gpu_ssbos.get(&self.handle) else {
+            return Err(AsBindGroupError::RetryNextUpdate);
+        };
+        let bindings = BindingResources(vec![(0, OwnedBindingResource::Buffer(gpu_ssbo.buffer.clone()))]);
+        Ok(UnpreparedBindGroup { bindings, data: () })
+    }
+
+    fn bind_group_layout_entries(_: &RenderDevice, _: bool) -> Vec<BindGroupLayoutEntry> {
+        vec![A::layout_entry(None)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texture(width: u32, height: u32, layers: u32, mips: u32) -> TextureDescriptor<'static> {
+        ImageTextureBuilder::default()
+            .size(Extent3d { width, height, depth_or_array_layers: layers })
+            .mip_level_count(mips)
+            .format(TextureFormat::Rgba8Unorm)
+            .descriptor()
+    }
+
+    fn view() -> ImageViewBuilder<'static> {
+        ImageViewBuilder::default()
+    }
+
+    #[test]
+    fn full_view_is_valid() {
+        assert!(view().try_descriptor(&texture(64, 64, 1, 1)).is_ok());
+        assert!(view().try_descriptor(&texture(64, 32, 8, 4)).is_ok());
+        assert!(view().format(Some(TextureFormat::Rgba8Unorm)).try_descriptor(&texture(64, 64, 1, 1)).is_ok());
+    }
+
+    #[test]
+    fn sub_range_view_is_valid() {
+        let texture = texture(64, 64, 8, 4);
+        let sub_range = view()
+            .dimension(Some(TextureViewDimension::D2Array))
+            .base_mip_level(1)
+            .mip_level_count(Some(2))
+            .base_array_layer(2)
+            .array_layer_count(Some(3));
+        assert!(sub_range.try_descriptor(&texture).is_ok());
+        // the last mip and layer, with the counts resolving to what's left
+        let last = view().dimension(Some(TextureViewDimension::D2)).base_mip_level(3).base_array_layer(7);
+        assert!(last.try_descriptor(&texture).is_ok());
+    }
+
+    #[test]
+    fn reinterpreted_format_needs_view_formats() {
+        let srgb = || view().format(Some(TextureFormat::Rgba8UnormSrgb));
+        let mut texture = texture(64, 64, 1, 1);
+        assert_eq!(
+            srgb().try_descriptor(&texture),
+            Err(ImageViewError::Format { view: TextureFormat::Rgba8UnormSrgb, texture: TextureFormat::Rgba8Unorm }),
+        );
+        texture.view_formats = &[TextureFormat::Rgba8UnormSrgb];
+        assert!(srgb().try_descriptor(&texture).is_ok());
+    }
+
+    struct Tiles;
+
+    impl Compute for Tiles {
+        const COMPUTE_SHADER_PATH: &'static str = "tiles.wgsl";
+        const WORKGROUP_SIZE: UVec3 = UVec3::new(16, 8, 1);
+    }
+
+    #[test]
+    fn workgroups_round_up_to_cover_the_extent() {
+        assert_eq!(Tiles::workgroups(UVec3::new(64, 64, 1)), UVec3::new(4, 8, 1));
+        assert_eq!(Tiles::workgroups(UVec3::new(65, 57, 3)), UVec3::new(5, 8, 3));
+        assert_eq!(Tiles::workgroups(UVec3::new(1, 1, 1)), UVec3::ONE);
+        // nothing to cover on one axis means no workgroups, which ComputeNode skips dispatching
+        assert_eq!(Tiles::workgroups(UVec3::new(64, 0, 1)), UVec3::new(4, 0, 1));
+    }
+
+    #[test]
+    fn mip_range_out_of_bounds() {
+        let texture = texture(64, 64, 1, 4);
+        let error = |base, count| Err(ImageViewError::MipRange { base, count, available: 4 });
+        assert_eq!(view().base_mip_level(4).try_descriptor(&texture), error(4, None));
+        assert_eq!(view().base_mip_level(2).mip_level_count(Some(3)).try_descriptor(&texture), error(2, Some(3)));
+        assert_eq!(view().mip_level_count(Some(0)).try_descriptor(&texture), error(0, Some(0)));
+        // would overflow u32 rather than run past the last mip
+        assert_eq!(view().base_mip_level(1).mip_level_count(Some(u32::MAX)).try_descriptor(&texture), error(1, Some(u32::MAX)));
+    }
+
+    #[test]
+    fn layer_range_out_of_bounds() {
+        let texture = texture(64, 64, 8, 1);
+        let error = |base, count| Err(ImageViewError::LayerRange { base, count, available: 8 });
+        assert_eq!(view().base_array_layer(8).try_descriptor(&texture), error(8, None));
+        assert_eq!(view().base_array_layer(4).array_layer_count(Some(5)).try_descriptor(&texture), error(4, Some(5)));
+        assert_eq!(view().array_layer_count(Some(0)).try_descriptor(&texture), error(0, Some(0)));
+        assert_eq!(view().base_array_layer(1).array_layer_count(Some(u32::MAX)).try_descriptor(&texture), error(1, Some(u32::MAX)));
+    }
+
+    #[test]
+    fn dimension_incompatible_with_texture() {
+        assert_eq!(
+            view().dimension(Some(TextureViewDimension::D3)).try_descriptor(&texture(64, 64, 1, 1)),
+            Err(ImageViewError::Dimension { view: TextureViewDimension::D3, texture: TextureDimension::D2 }),
+        );
+        let mut volume = texture(16, 16, 16, 1);
+        volume.dimension = TextureDimension::D3;
+        assert!(view().try_descriptor(&volume).is_ok());
+        assert_eq!(
+            view().dimension(Some(TextureViewDimension::D2)).try_descriptor(&volume),
+            Err(ImageViewError::Dimension { view: TextureViewDimension::D2, texture: TextureDimension::D3 }),
+        );
+    }
+
+    #[test]
+    fn layer_count_doesnt_fit_dimension() {
+        let error = |view, layers| Err(ImageViewError::LayerCount { view, layers });
+        let d2 = view().dimension(Some(TextureViewDimension::D2));
+        assert_eq!(d2.try_descriptor(&texture(64, 64, 8, 1)), error(TextureViewDimension::D2, 8));
+        let cube = view().dimension(Some(TextureViewDimension::Cube));
+        assert_eq!(cube.try_descriptor(&texture(64, 64, 4, 1)), error(TextureViewDimension::Cube, 4));
+        let cube_array = view().dimension(Some(TextureViewDimension::CubeArray));
+        assert_eq!(cube_array.try_descriptor(&texture(64, 64, 8, 1)), error(TextureViewDimension::CubeArray, 8));
+    }
+
+    #[test]
+    fn cube_views_need_square_layers() {
+        let cube = || view().dimension(Some(TextureViewDimension::Cube));
+        let cube_array = || view().dimension(Some(TextureViewDimension::CubeArray));
+        assert!(cube().try_descriptor(&texture(64, 64, 6, 1)).is_ok());
+        assert!(cube_array().try_descriptor(&texture(64, 64, 12, 1)).is_ok());
+        assert_eq!(cube().try_descriptor(&texture(64, 32, 6, 1)), Err(ImageViewError::CubeNotSquare { width: 64, height: 32 }));
+        assert_eq!(cube_array().try_descriptor(&texture(32, 64, 12, 1)), Err(ImageViewError::CubeNotSquare { width: 32, height: 64 }));
+    }
+
+    #[test]
+    fn aspect_missing_from_format() {
+        assert_eq!(
+            view().aspect(TextureAspect::DepthOnly).try_descriptor(&texture(64, 64, 1, 1)),
+            Err(ImageViewError::Aspect { aspect: TextureAspect::DepthOnly, format: TextureFormat::Rgba8Unorm }),
+        );
+        let mut depth_stencil = texture(64, 64, 1, 1);
+        depth_stencil.format = TextureFormat::Depth24PlusStencil8;
+        let depth = view().aspect(TextureAspect::DepthOnly).format(Some(TextureFormat::Depth24Plus));
+        assert!(depth.try_descriptor(&depth_stencil).is_ok());
+        assert!(view().aspect(TextureAspect::StencilOnly).try_descriptor(&depth_stencil).is_ok());
+    }
+
+    #[test]
+    fn format_incompatible_with_texture() {
+        assert_eq!(
+            view().format(Some(TextureFormat::Rgba16Float)).try_descriptor(&texture(64, 64, 1, 1)),
+            Err(ImageViewError::Format { view: TextureFormat::Rgba16Float, texture: TextureFormat::Rgba8Unorm }),
+        );
+    }
+
+    #[test]
+    fn usage_not_subset_of_texture() {
+        let texture = texture(64, 64, 1, 1);
+        assert!(view().usage(Some(TextureUsages::TEXTURE_BINDING)).try_descriptor(&texture).is_ok());
+        assert_eq!(
+            view().usage(Some(TextureUsages::STORAGE_BINDING)).try_descriptor(&texture),
+            Err(ImageViewError::Usage { view: TextureUsages::STORAGE_BINDING, texture: texture.usage }),
+        );
+    }
+
+    struct SpecializedPass;
+
+    impl Pass for SpecializedPass {
+        type Binds = ();
+        type Constants = ();
+    }
+
+    impl Raster for SpecializedPass {
+        const VERTEX_FRAGMENT_SHADER_PATH: &'static str = "specialized.wgsl";
+
+        fn fragment_targets() -> Vec<Option<ColorTargetState>> {
+            vec![Some(TextureFormat::bevy_default().into()), None]
+        }
+    }
+
+    impl SpecializedRaster for SpecializedPass {}
+
+    fn raster_descriptor<P: Raster>() -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: None,
+            layout: vec![],
+            push_constant_ranges: vec![],
+            vertex: VertexState { shader: default(), shader_defs: vec![], entry_point: "vertex".into(), buffers: P::vertex_buffers() },
+            primitive: P::primitive(),
+            depth_stencil: P::depth_stencil(),
+            multisample: P::multisample(),
+            fragment: Some(FragmentState { shader: default(), shader_defs: vec![], entry_point: "fragment".into(), targets: P::fragment_targets() }),
+            zero_initialize_workgroup_memory: false,
+        }
+    }
+
+    #[test]
+    fn specialize_follows_view_format_and_samples() {
+        let mut descriptor = raster_descriptor::<SpecializedPass>();
+        let key = RasterKey { format: TextureFormat::Rgba16Float, samples: 4, bits: 0, shader_defs: vec![] };
+        SpecializedPass::specialize(key, &mut descriptor);
+        assert_eq!(descriptor.multisample.count, 4);
+        let targets = descriptor.fragment.unwrap().targets;
+        assert_eq!(targets[0].as_ref().map(|target| target.format), Some(TextureFormat::Rgba16Float));
+        assert!(targets[1].is_none());
+    }
+
+    #[derive(ShaderType)]
+    struct Tint {
+        color: Vec4,
+        strength: f32,
+    }
+
+    impl PushConstants for Tint {
+        const STAGES: ShaderStages = ShaderStages::VERTEX_FRAGMENT;
+    }
+
+    #[test]
+    fn push_constant_range_follows_uniform_layout() {
+        assert_eq!(<() as MaybePushConstants>::push_constant_range(), None);
+        // the f32 after the vec4 pads the struct out to the next 16 bytes
+        assert_eq!(Tint::push_constant_range(), Some(PushConstantRange { stages: ShaderStages::VERTEX_FRAGMENT, range: 0..32 }));
+        assert_eq!(encode_push_constants(&Tint { color: Vec4::ONE, strength: 0.5 }).len(), 32);
+    }
+
+    #[test]
+    fn push_constants_fall_back_without_the_feature_or_room() {
+        let range = Tint::push_constant_range().unwrap();
+        let limits = |max_push_constant_size| WgpuLimits { max_push_constant_size, ..default() };
+        assert!(supports_push_constants(WgpuFeatures::PUSH_CONSTANTS, &limits(128), &range));
+        assert!(supports_push_constants(WgpuFeatures::PUSH_CONSTANTS, &limits(32), &range));
+        assert!(!supports_push_constants(WgpuFeatures::PUSH_CONSTANTS, &limits(16), &range));
+        assert!(!supports_push_constants(WgpuFeatures::empty(), &limits(128), &range));
+    }
+
+    #[test]
+    fn fallback_is_a_dynamic_uniform_in_the_constants_stages() {
+        let entries = fallback_layout_entries(&Tint::push_constant_range().unwrap());
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].binding, 0);
+        assert_eq!(entries[0].visibility, ShaderStages::VERTEX_FRAGMENT);
+        assert_eq!(entries[0].ty, BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: true,
+            min_binding_size: NonZeroU64::new(32),
+        });
+        // each value starts on an offset the device accepts
+        let limits = WgpuLimits { min_uniform_buffer_offset_alignment: 256, ..default() };
+        assert_eq!(fallback_stride(32, &limits), 256);
+        assert_eq!(fallback_stride(300, &limits), 512);
+    }
+
+    #[test]
+    fn storage_access_layout_and_visibility() {
+        let read_only = ReadOnly::layout_entry(NonZeroU64::new(16));
+        assert_eq!(read_only.binding, 0);
+        assert_eq!(read_only.visibility, ShaderStages::all());
+        assert_eq!(read_only.ty, BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: NonZeroU64::new(16),
+        });
+        // writable storage stays out of the vertex stage
+        let read_write = ReadWrite::layout_entry(None);
+        assert_eq!(read_write.binding, 0);
+        assert_eq!(read_write.visibility, ShaderStages::FRAGMENT | ShaderStages::COMPUTE);
+        assert_eq!(read_write.ty, BindingType::Buffer {
+            ty: BufferBindingType::Storage { read_only: false },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        });
+    }
+
+    type SparseBinds = (Group<2, ViewUniformBinds>,);
+
+    fn device_world() -> World {
+        let mut world = World::new();
+        world.insert_resource(crate::validate::fallback_render_device().expect("no wgpu adapter to create bind groups on"));
+        world.init_resource::<EmptyBindGroup>();
+        world
+    }
+
+    #[test]
+    fn group_overrides_tuple_position() {
+        assert_eq!(SparseBinds::groups(), vec![2]);
+        assert_eq!(<(ViewUniformBinds, Group<3, ViewUniformBinds>)>::groups(), vec![0, 3]);
+        assert_eq!(group_count(&SparseBinds::groups()), 3);
+        assert_eq!(group_count(&[]), 0);
+    }
+
+    #[test]
+    fn groups_are_placed_at_their_index() {
+        assert_eq!(place_at_groups("Binds", vec![2, 0], vec!['a', 'b']), vec![Some('b'), None, Some('a')]);
+        assert_eq!(place_at_groups("Binds", vec![], Vec::<char>::new()), vec![]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Binds binds group 1 more than once")]
+    fn duplicate_groups_panic() {
+        place_at_groups("Binds", vec![1, 1], vec!['a', 'b']);
+    }
+
+    #[test]
+    fn sparse_groups_get_empty_layouts_and_bind_groups() {
+        let world = device_world();
+        let empty = world.resource::<EmptyBindGroup>();
+        let layouts = SparseBinds::into_layout(&world);
+        let dense = pipeline_layouts::<SparseBinds>(empty, &layouts);
+        let ids = dense.iter().map(BindGroupLayout::id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![empty.layout.id(), empty.layout.id(), layouts[0].id()]);
+
+        let device = world.resource::<RenderDevice>();
+        let bind_group = device.create_bind_group("sparse", &empty.layout, &[]);
+        let bound = BoundGroup { bind_group: bind_group.clone(), offsets: vec![256] };
+        let dense = dense_bind_groups::<SparseBinds>(vec![bound], empty);
+        let ids = dense.iter().map(|bound| bound.bind_group.id()).collect::<Vec<_>>();
+        assert_eq!(ids, vec![empty.bind_group.id(), empty.bind_group.id(), bind_group.id()]);
+        // only the bound group carries dynamic offsets
+        assert_eq!(dense.iter().map(|bound| bound.offsets.len()).collect::<Vec<_>>(), vec![0, 0, 1]);
+    }
+}
@@ -0,0 +1,383 @@
+use std::{any::*, fmt, marker::PhantomData};
+use bevy::prelude::*;
+use bevy::render::render_resource::*;
+use naga::{proc::Layouter, ArraySize, Module, Type, TypeInner};
+
+/// A `ShaderType` with a WGSL counterpart, so the shader side of a params struct is generated from the Rust one
+/// instead of kept in sync by hand. Structs implement it through `wgsl_struct!`.
+pub trait WgslType: ShaderType {
+    /// Name of the type in WGSL, e.g. `vec4<f32>`, `array<u32, 4>` or the struct's name.
+    fn wgsl_name() -> String;
+
+    /// Appends the struct definitions this type needs, nested ones first, skipping ones already in `structs`.
+    fn wgsl_structs(_structs: &mut Vec<String>) {}
+
+    /// Name and encase offset of each member, in order, empty for anything but structs.
+    fn wgsl_members() -> Vec<(&'static str, u64)> { vec![] }
+
+    /// Compares this type's layout in `wgsl` against encase's, see `validate_wgsl_layout`.
+    /// Checks the `wgsl_members` offsets and the size, `wgsl_struct!` and arrays also check the types they contain.
+    fn validate_layout(wgsl: WgslLayout) -> Result<(), WgslLayoutError> {
+        validate_members::<Self>(wgsl, &[])
+    }
+}
+
+// lets `wgsl_struct!` name encase's trait from any crate
+#[doc(hidden)]
+pub use bevy::render::render_resource::ShaderType as EncaseShaderType;
+
+macro_rules! impl_wgsl_type {
+    ($($T:ty => $name:literal),* $(,)?) => {
+        $(impl WgslType for $T {
+            fn wgsl_name() -> String { $name.into() }
+        })*
+    };
+}
+
+impl_wgsl_type!(
+    f32 => "f32", u32 => "u32", i32 => "i32",
+    Vec2 => "vec2<f32>", Vec3 => "vec3<f32>", Vec4 => "vec4<f32>",
+    UVec2 => "vec2<u32>", UVec3 => "vec3<u32>", UVec4 => "vec4<u32>",
+    IVec2 => "vec2<i32>", IVec3 => "vec3<i32>", IVec4 => "vec4<i32>",
+    Mat2 => "mat2x2<f32>", Mat3 => "mat3x3<f32>", Mat4 => "mat4x4<f32>",
+    LinearRgba => "vec4<f32>",
+);
+
+impl<T: WgslType + ShaderSize, const N: usize> WgslType for [T; N] {
+    fn wgsl_name() -> String { format!("array<{}, {N}>", T::wgsl_name()) }
+    fn wgsl_structs(structs: &mut Vec<String>) { T::wgsl_structs(structs) }
+    fn validate_layout(wgsl: WgslLayout) -> Result<(), WgslLayoutError> {
+        validate_members::<Self>(wgsl, &[])?;
+        wgsl.element().map_or(Ok(()), T::validate_layout)
+    }
+}
+
+/// Runtime-sized array, only valid as the last field of a struct bound as storage.
+impl<T: WgslType + ShaderSize> WgslType for Vec<T> {
+    fn wgsl_name() -> String { format!("array<{}>", T::wgsl_name()) }
+    fn wgsl_structs(structs: &mut Vec<String>) { T::wgsl_structs(structs) }
+    fn validate_layout(wgsl: WgslLayout) -> Result<(), WgslLayoutError> {
+        wgsl.element().map_or(Ok(()), T::validate_layout)
+    }
+}
+
+/// Declares a struct along with its `WgslType` impl, derive `ShaderType` on it as usual.
+/// Fields are emitted in declaration order without explicit `@align`/`@size`, since encase lays structs
+/// out with the same WGSL rules; `validate_wgsl_layout` checks that the offsets and sizes agree.
+/// ```ignore
+/// wgsl_struct! {
+///     #[derive(ShaderType)]
+///     pub struct Params {
+///         quad: [Vec4; 4],
+///         brush: u32,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! wgsl_struct {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field_vis:vis $field:ident: $T:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$field_meta])* $field_vis $field: $T),*
+        }
+
+        impl $crate::wgsl::WgslType for $name {
+            fn wgsl_name() -> String { stringify!($name).into() }
+            fn wgsl_structs(structs: &mut Vec<String>) {
+                $(<$T as $crate::wgsl::WgslType>::wgsl_structs(structs);)*
+                let mut definition = format!("struct {} {{\n", stringify!($name));
+                $(definition += &format!("    {}: {},\n", stringify!($field), <$T as $crate::wgsl::WgslType>::wgsl_name());)*
+                definition += "}\n";
+                if !structs.contains(&definition) {
+                    structs.push(definition);
+                }
+            }
+            fn wgsl_members() -> Vec<(&'static str, u64)> {
+                let names = [$(stringify!($field)),*];
+                (0..names.len())
+                    .map(|index| (names[index], <$name as $crate::wgsl::EncaseShaderType>::METADATA.offset(index)))
+                    .collect()
+            }
+            fn validate_layout(wgsl: $crate::wgsl::WgslLayout) -> Result<(), $crate::wgsl::WgslLayoutError> {
+                $crate::wgsl::validate_members::<Self>(wgsl, &[$(<$T as $crate::wgsl::WgslType>::validate_layout),*])
+            }
+        }
+    };
+}
+
+/// WGSL definitions of every struct T needs, separated by blank lines.
+pub fn wgsl_source<T: WgslType>() -> String {
+    let mut structs = vec![];
+    T::wgsl_structs(&mut structs);
+    structs.join("\n")
+}
+
+/// `wgsl_source` as a naga_oil module, importable from other shaders as `#import {import_path}::{Name}`.
+pub fn wgsl_module<T: WgslType>(import_path: &str) -> String {
+    format!("#define_import_path {import_path}\n\n{}", wgsl_source::<T>())
+}
+
+/// Parses the generated WGSL with naga and compares the layout naga's `Layouter` gives T against encase's,
+/// i.e. the offset of every member of a struct and then its `min_size`. Structs nested in T, directly or as
+/// array elements, are compared the same way. Sizes of runtime-sized types aren't compared, only their members.
+pub fn validate_wgsl_layout<T: WgslType>() -> Result<(), WgslLayoutError> {
+    let name = type_name::<T>();
+    let source = format!("{}\nvar<private> wgsl_layout_check: {};\n", wgsl_source::<T>(), T::wgsl_name());
+    let module = naga::front::wgsl::parse_str(&source)
+        .map_err(|error| WgslLayoutError::Parse { name, error: error.emit_to_string(&source) })?;
+    let mut layouter = Layouter::default();
+    layouter.update(module.to_ctx())
+        .map_err(|error| WgslLayoutError::Layout { name, error: error.to_string() })?;
+    let (_, check) = module.global_variables.iter()
+        .find(|(_, variable)| variable.name.as_deref() == Some("wgsl_layout_check"))
+        .expect("layout check variable is declared above");
+    T::validate_layout(WgslLayout { module: &module, layouter: &layouter, ty: check.ty })
+}
+
+/// A type of the WGSL module parsed by `validate_wgsl_layout`, along with its layout.
+#[derive(Clone, Copy)]
+pub struct WgslLayout<'a> {
+    module: &'a Module,
+    layouter: &'a Layouter,
+    ty: naga::Handle<Type>,
+}
+
+impl<'a> WgslLayout<'a> {
+    fn at(self, ty: naga::Handle<Type>) -> Self {
+        Self { ty, ..self }
+    }
+
+    /// Name, offset and type of each member, empty for anything but structs.
+    pub fn members(self) -> Vec<(&'a str, u64, Self)> {
+        match &self.module.types[self.ty].inner {
+            TypeInner::Struct { members, .. } => members.iter()
+                .map(|member| (member.name.as_deref().unwrap_or_default(), member.offset as u64, self.at(member.ty)))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Element type of an array.
+    pub fn element(self) -> Option<Self> {
+        match self.module.types[self.ty].inner {
+            TypeInner::Array { base, .. } => Some(self.at(base)),
+            _ => None,
+        }
+    }
+
+    pub fn size(self) -> u64 {
+        self.layouter[self.ty].size as u64
+    }
+
+    /// Runtime-sized arrays and structs ending in one, whose size depends on the bound buffer.
+    pub fn is_runtime_sized(self) -> bool {
+        match &self.module.types[self.ty].inner {
+            TypeInner::Array { size: ArraySize::Dynamic, .. } => true,
+            TypeInner::Struct { members, .. } => members.last().is_some_and(|last| self.at(last.ty).is_runtime_sized()),
+            _ => false,
+        }
+    }
+}
+
+/// A member type's `WgslType::validate_layout`, see `validate_members`.
+pub type ValidateLayout = fn(WgslLayout) -> Result<(), WgslLayoutError>;
+
+/// Compares the offsets of T's `wgsl_members` and T's size against `wgsl`, then validates each member's type
+/// with the function at its index in `members`. Used by `wgsl_struct!`.
+pub fn validate_members<T: WgslType + ?Sized>(
+    wgsl: WgslLayout,
+    members: &[ValidateLayout],
+) -> Result<(), WgslLayoutError> {
+    let name = type_name::<T>();
+    for (index, ((member, offset, layout), (_, encase))) in wgsl.members().into_iter().zip(T::wgsl_members()).enumerate() {
+        if offset != encase {
+            return Err(WgslLayoutError::Offset { name, member: member.into(), wgsl: offset, encase });
+        }
+        if let Some(validate) = members.get(index) {
+            validate(layout)?;
+        }
+    }
+    if wgsl.is_runtime_sized() {
+        return Ok(());
+    }
+    let (wgsl, encase) = (wgsl.size(), T::min_size().get());
+    match wgsl == encase {
+        true => Ok(()),
+        false => Err(WgslLayoutError::Size { name, wgsl, encase }),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WgslLayoutError {
+    /// naga couldn't parse the generated source, e.g. a field named after a WGSL keyword.
+    Parse { name: &'static str, error: String },
+    /// naga couldn't lay out a type, e.g. an array of a runtime-sized type.
+    Layout { name: &'static str, error: String },
+    /// A struct member is at a different offset in WGSL than where encase writes it.
+    Offset { name: &'static str, member: String, wgsl: u64, encase: u64 },
+    /// The WGSL type and the Rust type have different sizes.
+    Size { name: &'static str, wgsl: u64, encase: u64 },
+}
+
+impl fmt::Display for WgslLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse { name, error } => write!(f, "generated WGSL for {name} doesn't parse: {error}"),
+            Self::Layout { name, error } => write!(f, "generated WGSL for {name} can't be laid out: {error}"),
+            Self::Offset { name, member, wgsl, encase } =>
+                write!(f, "generated WGSL for {name} has `{member}` at offset {wgsl} but encase writes it at {encase}"),
+            Self::Size { name, wgsl, encase } =>
+                write!(f, "generated WGSL for {name} is {wgsl} bytes but encase writes {encase} bytes"),
+        }
+    }
+}
+
+impl std::error::Error for WgslLayoutError {}
+
+/// Registers the generated WGSL of T as a shader module importable at `import_path`.
+/// The layout is checked against encase when the plugin is built, a mismatch panics since every shader
+/// importing the module would read the wrong bytes.
+pub struct WgslModulePlugin<T> {
+    import_path: &'static str,
+    marker: PhantomData<T>,
+}
+
+impl<T> WgslModulePlugin<T> {
+    pub fn new(import_path: &'static str) -> Self {
+        Self { import_path, marker: PhantomData }
+    }
+}
+
+/// Keeps the generated module of T loaded, it's dropped from `Assets<Shader>` along with this resource.
+#[derive(Resource)]
+pub struct WgslModule<T> {
+    pub handle: Handle<Shader>,
+    marker: PhantomData<T>,
+}
+
+impl<T: WgslType + Send + Sync + 'static> Plugin for WgslModulePlugin<T> {
+    fn build(&self, app: &mut App) {
+        if let Err(error) = validate_wgsl_layout::<T>() {
+            panic!("{error}");
+        }
+        let path = format!("{}.wgsl", self.import_path.replace("::", "/"));
+        let shader = Shader::from_wgsl(wgsl_module::<T>(self.import_path), path);
+        let handle = app.world_mut().resource_mut::<Assets<Shader>>().add(shader);
+        app.insert_resource(WgslModule::<T> { handle, marker: PhantomData });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::programs::draw::DrawParams;
+    use super::*;
+
+    wgsl_struct! {
+        #[derive(ShaderType)]
+        struct Padded {
+            a: f32,
+            b: Vec3,
+            c: f32,
+            d: Vec2,
+        }
+    }
+
+    // claims a vec2 where encase writes an f32, shifting b and c while the size stays 16
+    #[derive(ShaderType)]
+    struct Mismatched {
+        a: f32,
+        b: f32,
+        c: Vec2,
+    }
+
+    impl WgslType for Mismatched {
+        fn wgsl_name() -> String { "Mismatched".into() }
+        fn wgsl_structs(structs: &mut Vec<String>) {
+            structs.push("struct Mismatched {\n    a: vec2<f32>,\n    b: f32,\n    c: f32,\n}\n".into());
+        }
+        fn wgsl_members() -> Vec<(&'static str, u64)> { vec![("a", 0), ("b", 4), ("c", 8)] }
+    }
+
+    wgsl_struct! {
+        #[derive(ShaderType)]
+        struct Stroke {
+            padded: [Padded; 2],
+            count: u32,
+            last: Padded,
+        }
+    }
+
+    wgsl_struct! {
+        #[derive(ShaderType)]
+        struct Strokes {
+            count: u32,
+            #[size(runtime)]
+            strokes: Vec<Stroke>,
+        }
+    }
+
+    // Mismatched sits at the same offset in both, so only its own members give it away
+    wgsl_struct! {
+        #[derive(ShaderType)]
+        struct Wrapper {
+            scale: f32,
+            inner: Mismatched,
+        }
+    }
+
+    #[test]
+    fn draw_params_matches_encase() {
+        assert_eq!(
+            wgsl_source::<DrawParams>(),
+            "struct DrawParams {\n    quad: array<vec4<f32>, 4>,\n    brush: u32,\n}\n",
+        );
+        assert_eq!(DrawParams::wgsl_members(), vec![("quad", 0), ("brush", 64)]);
+        assert_eq!(validate_wgsl_layout::<DrawParams>(), Ok(()));
+    }
+
+    #[test]
+    fn vec3_padding_matches_encase() {
+        assert_eq!(
+            wgsl_source::<Padded>(),
+            "struct Padded {\n    a: f32,\n    b: vec3<f32>,\n    c: f32,\n    d: vec2<f32>,\n}\n",
+        );
+        // b is aligned to 16 and c packs into the last 4 bytes of its vec3
+        assert_eq!(Padded::wgsl_members(), vec![("a", 0), ("b", 16), ("c", 28), ("d", 32)]);
+        assert_eq!(Padded::min_size().get(), 48);
+        assert_eq!(validate_wgsl_layout::<Padded>(), Ok(()));
+    }
+
+    #[test]
+    fn member_offset_mismatch() {
+        assert_eq!(
+            validate_wgsl_layout::<Mismatched>(),
+            Err(WgslLayoutError::Offset { name: type_name::<Mismatched>(), member: "b".into(), wgsl: 8, encase: 4 }),
+        );
+    }
+
+    #[test]
+    fn nested_structs_and_arrays_match_encase() {
+        assert_eq!(
+            wgsl_source::<Stroke>(),
+            "struct Padded {\n    a: f32,\n    b: vec3<f32>,\n    c: f32,\n    d: vec2<f32>,\n}\n\n\
+             struct Stroke {\n    padded: array<Padded, 2>,\n    count: u32,\n    last: Padded,\n}\n",
+        );
+        assert_eq!(Stroke::wgsl_members(), vec![("padded", 0), ("count", 96), ("last", 112)]);
+        assert_eq!(validate_wgsl_layout::<Stroke>(), Ok(()));
+        assert_eq!(validate_wgsl_layout::<[Stroke; 3]>(), Ok(()));
+        assert_eq!(validate_wgsl_layout::<Strokes>(), Ok(()));
+    }
+
+    #[test]
+    fn nested_member_offset_mismatch() {
+        let mismatch = Err(WgslLayoutError::Offset { name: type_name::<Mismatched>(), member: "b".into(), wgsl: 8, encase: 4 });
+        assert_eq!(Wrapper::wgsl_members(), vec![("scale", 0), ("inner", 8)]);
+        assert_eq!(validate_wgsl_layout::<Wrapper>(), mismatch);
+        assert_eq!(validate_wgsl_layout::<[Mismatched; 2]>(), mismatch);
+    }
+}